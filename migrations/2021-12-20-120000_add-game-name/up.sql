@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+alter table games add column name text;
+update games set name = 'room-' || id;
+alter table games alter column name set not null;
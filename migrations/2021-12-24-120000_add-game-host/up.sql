@@ -0,0 +1,3 @@
+-- Your SQL goes here
+
+alter table games add column host_id bigint default null references users(id) on delete set null;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+
+alter table games add column is_private boolean not null default false;
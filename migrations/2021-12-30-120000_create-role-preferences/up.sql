@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+create table role_preferences(
+    user_id bigint not null primary key references users(id) on delete cascade,
+    preferred text[] not null default '{}',
+    avoided text[] not null default '{}'
+);
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+
+alter table users add column is_bot boolean not null default false;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+create table mode_stats(
+    user_id bigint not null references users(id) on delete cascade,
+    mode text not null,
+    version integer not null,
+    win integer not null default 0,
+    lose integer not null default 0,
+    primary key (user_id, mode, version)
+);
@@ -0,0 +1,16 @@
+use std::env;
+use std::process::exit;
+
+// usage: check-roles [config path] [samples per size]
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    let path = args
+        .get(1)
+        .map(|s| s.as_str())
+        .unwrap_or("./jsons/role-config.json");
+    let samples = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(10000);
+
+    if !werewolf_services::check_role_config(path, samples, true) {
+        exit(1);
+    }
+}
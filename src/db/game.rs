@@ -9,14 +9,79 @@ use crate::{models::game::Game, schema::games};
 
 use super::channel;
 
-pub fn create(conn: &PgConnection, id: i64) -> QueryResult<Game> {
+pub fn create(
+    conn: &PgConnection,
+    id: i64,
+    name: &str,
+    settings: &str,
+    is_private: bool,
+) -> QueryResult<Game> {
     diesel::insert_into(games::table)
-        .values(&Game { id })
+        .values(&Game {
+            id,
+            name: name.to_string(),
+            settings: settings.to_string(),
+            host_id: None,
+            is_private,
+            phase_deadline: None,
+            seed: None,
+            snapshot: None,
+        })
         .get_result(conn)
 }
 
-pub fn get(conn: &PgConnection) -> Option<Game> {
-    games::table.first(conn).ok()
+pub fn set_host(
+    conn: &PgConnection,
+    id: i64,
+    host_id: Option<i64>,
+) -> QueryResult<usize> {
+    diesel::update(games::table.find(id))
+        .set(games::host_id.eq(host_id))
+        .execute(conn)
+}
+
+pub fn set_phase_deadline(
+    conn: &PgConnection,
+    id: i64,
+    deadline: Option<i64>,
+) -> QueryResult<usize> {
+    diesel::update(games::table.find(id))
+        .set(games::phase_deadline.eq(deadline))
+        .execute(conn)
+}
+
+pub fn set_seed(
+    conn: &PgConnection,
+    id: i64,
+    seed: Option<i64>,
+) -> QueryResult<usize> {
+    diesel::update(games::table.find(id))
+        .set(games::seed.eq(seed))
+        .execute(conn)
+}
+
+pub fn set_snapshot(
+    conn: &PgConnection,
+    id: i64,
+    snapshot: Option<String>,
+) -> QueryResult<usize> {
+    diesel::update(games::table.find(id))
+        .set(games::snapshot.eq(snapshot))
+        .execute(conn)
+}
+
+pub fn update_settings(
+    conn: &PgConnection,
+    id: i64,
+    settings: &str,
+) -> QueryResult<usize> {
+    diesel::update(games::table.find(id))
+        .set(games::settings.eq(settings))
+        .execute(conn)
+}
+
+pub fn get_all(conn: &PgConnection) -> QueryResult<Vec<Game>> {
+    games::table.order(games::id).get_results(conn)
 }
 
 pub fn delete(conn: &PgConnection, id: i64) -> QueryResult<usize> {
@@ -72,6 +137,20 @@ pub fn remove_user(
     diesel::delete(filter).execute(conn)
 }
 
+pub fn replace_user(
+    conn: &PgConnection,
+    game_id: i64,
+    old_user_id: i64,
+    new_user_id: i64,
+) -> QueryResult<usize> {
+    let filter = game_users::table
+        .filter(game_users::game_id.eq(game_id))
+        .filter(game_users::user_id.eq(old_user_id));
+    diesel::update(filter)
+        .set(game_users::user_id.eq(new_user_id))
+        .execute(conn)
+}
+
 #[allow(dead_code)]
 pub fn get_channels(
     conn: &PgConnection,
@@ -4,8 +4,8 @@ use diesel::prelude::*;
 use diesel::PgConnection;
 use serde::{Deserialize, Serialize};
 
-use crate::models::user::User;
-use crate::schema::users;
+use crate::models::user::{ModeStat, RolePreference, User};
+use crate::schema::{mode_stats, role_preferences, users};
 
 use super::channel::set_pers;
 
@@ -16,6 +16,7 @@ struct NewUser<'a> {
     username: &'a str,
     hash_passwd: &'a str,
     avatar_url: Option<&'a str>,
+    is_bot: bool,
 }
 
 pub fn create(
@@ -35,6 +36,7 @@ pub fn create(
         username,
         hash_passwd,
         avatar_url,
+        is_bot: false,
     };
 
     let user = diesel::insert_into(users::table)
@@ -54,6 +56,7 @@ pub fn login(
 ) -> Result<User, &'static str> {
     let user = users::table
         .filter(users::username.eq(username))
+        .filter(users::is_bot.eq(false))
         .get_result::<User>(conn)
         .map_err(|_| "login failed")?;
 
@@ -77,6 +80,31 @@ pub fn get_info(conn: &PgConnection, user_id: i64) -> QueryResult<User> {
     users::table.find(user_id).get_result::<User>(conn)
 }
 
+// bots can't log in, their password hash is never a valid scrypt hash
+pub fn create_bot(
+    conn: &PgConnection,
+    id: i64,
+    username: &str,
+) -> QueryResult<User> {
+    diesel::insert_into(users::table)
+        .values(&NewUser {
+            id,
+            username,
+            hash_passwd: "",
+            avatar_url: None,
+            is_bot: true,
+        })
+        .get_result::<User>(conn)
+}
+
+pub fn get_bots(conn: &PgConnection) -> QueryResult<Vec<User>> {
+    users::table
+        .filter(users::is_bot.eq(true))
+        .order(users::id)
+        .get_results::<User>(conn)
+}
+
+// bots stay out of the ranking
 pub fn update_win(
     conn: &PgConnection,
     user_id: i64,
@@ -84,6 +112,9 @@ pub fn update_win(
 ) -> QueryResult<usize> {
     let mut user = users::table.find(user_id).get_result::<User>(conn)?;
     let user_filter = users::table.find(user_id);
+    if user.is_bot {
+        return Ok(0);
+    }
 
     if is_winner {
         user.win = user.win.map(|w| w + 1);
@@ -95,3 +126,79 @@ pub fn update_win(
         .set((users::win.eq(user.win), users::lose.eq(user.lose)))
         .execute(conn)
 }
+
+// the same result counted again under the mode the game was played in
+pub fn update_mode_win(
+    conn: &PgConnection,
+    user_id: i64,
+    mode: &str,
+    version: u32,
+    is_winner: bool,
+) -> QueryResult<usize> {
+    let user = users::table.find(user_id).get_result::<User>(conn)?;
+    if user.is_bot {
+        return Ok(0);
+    }
+
+    let (win, lose) = if is_winner { (1, 0) } else { (0, 1) };
+    diesel::insert_into(mode_stats::table)
+        .values(&ModeStat {
+            user_id,
+            mode: mode.to_string(),
+            version: version as i32,
+            win,
+            lose,
+        })
+        .on_conflict((
+            mode_stats::user_id,
+            mode_stats::mode,
+            mode_stats::version,
+        ))
+        .do_update()
+        .set((
+            mode_stats::win.eq(mode_stats::win + win),
+            mode_stats::lose.eq(mode_stats::lose + lose),
+        ))
+        .execute(conn)
+}
+
+pub fn get_role_prefs(
+    conn: &PgConnection,
+    user_id: i64,
+) -> QueryResult<RolePreference> {
+    role_preferences::table
+        .find(user_id)
+        .get_result::<RolePreference>(conn)
+        .optional()
+        .map(|prefs| {
+            prefs.unwrap_or(RolePreference {
+                user_id,
+                preferred: vec![],
+                avoided: vec![],
+            })
+        })
+}
+
+pub fn get_all_role_prefs(
+    conn: &PgConnection,
+    user_ids: &[i64],
+) -> QueryResult<Vec<RolePreference>> {
+    role_preferences::table
+        .filter(role_preferences::user_id.eq_any(user_ids))
+        .get_results::<RolePreference>(conn)
+}
+
+pub fn set_role_prefs(
+    conn: &PgConnection,
+    prefs: &RolePreference,
+) -> QueryResult<RolePreference> {
+    diesel::insert_into(role_preferences::table)
+        .values(prefs)
+        .on_conflict(role_preferences::user_id)
+        .do_update()
+        .set((
+            role_preferences::preferred.eq(&prefs.preferred),
+            role_preferences::avoided.eq(&prefs.avoided),
+        ))
+        .get_result(conn)
+}
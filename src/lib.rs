@@ -19,6 +19,31 @@ use auth::Auth;
 use error::Res;
 use ws::{ChatServer, WsClient};
 
+// checks the random role config and prints the expected deals, false when
+// some table size would fail to start
+pub fn check_role_config(path: &str, samples: usize, verbose: bool) -> bool {
+    use ws::game::characters::{analyzer, load_config};
+
+    let config = match load_config(path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("role config {}: {}", path, err);
+            return false;
+        }
+    };
+
+    let errors = analyzer::validate(&config, samples);
+    for err in errors.iter() {
+        eprintln!("role config {}: {}", path, err);
+    }
+    if verbose {
+        for report in analyzer::analyze(&config, samples, 0) {
+            print!("{}", report);
+        }
+    }
+    errors.is_empty()
+}
+
 async fn notfound_handle() -> Res {
     Err(ErrorBadRequest("resource not found"))
 }
@@ -43,6 +68,14 @@ pub async fn run() -> std::io::Result<()> {
 
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
+    // a broken config would only show up when some table fails to start
+    if !check_role_config(ws::game::characters::ROLE_CONFIG, 1000, false) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "broken role config, see check-roles",
+        ));
+    }
+
     let config = config::load()?;
     let app_state = config.app_state.clone();
     let db_pool = config.db_pool.clone();
@@ -68,6 +101,8 @@ pub async fn run() -> std::io::Result<()> {
                     .service(routes::user::get_all)
                     .service(routes::user::get_info)
                     .service(routes::user::get_pers)
+                    .service(routes::user::get_prefs)
+                    .service(routes::user::set_prefs)
                     .service(routes::user::send_msg),
             )
             .service(ws_handler)
@@ -36,7 +36,7 @@ pub struct UserChannelPermissionDisplay {
     pub sendable: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChannelPermission {
     pub channel_name: String,
     pub readable: bool,
@@ -62,7 +62,7 @@ impl ChatMsg {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DispChatMsg {
     pub message_id: String,
     pub user_id: String,
@@ -5,6 +5,13 @@ use crate::schema::{game_channels, game_users, games};
 #[derive(Serialize, Deserialize, Queryable, Insertable, Debug)]
 pub struct Game {
     pub id: i64,
+    pub name: String,
+    pub settings: String,
+    pub host_id: Option<i64>,
+    pub is_private: bool,
+    pub phase_deadline: Option<i64>,
+    pub seed: Option<i64>,
+    pub snapshot: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Queryable, Insertable, Debug)]
@@ -20,3 +27,11 @@ pub struct GameChannel {
     pub game_id: i64,
     pub channel_id: i64,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GameDisplay {
+    pub id: String,
+    pub name: String,
+    pub num_players: usize,
+    pub state: String,
+}
@@ -2,6 +2,7 @@ use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::auth::Auth;
+use crate::schema::{mode_stats, role_preferences};
 
 #[derive(Debug, Serialize, Deserialize, Queryable)]
 pub struct User {
@@ -12,6 +13,27 @@ pub struct User {
     pub avatar_url: Option<String>,
     pub win: Option<i32>,
     pub lose: Option<i32>,
+    pub is_bot: bool,
+}
+
+// ranked roles a user hopes for and roles they'd rather not get
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable, Clone)]
+#[table_name = "role_preferences"]
+pub struct RolePreference {
+    pub user_id: i64,
+    pub preferred: Vec<String>,
+    pub avoided: Vec<String>,
+}
+
+// wins and losses of a user in games played under one mode preset
+#[derive(Debug, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "mode_stats"]
+pub struct ModeStat {
+    pub user_id: i64,
+    pub mode: String,
+    pub version: i32,
+    pub win: i32,
+    pub lose: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,7 +41,7 @@ pub struct UserAuth {
     pub token: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserDisplay {
     pub id: String,
     pub username: String,
@@ -27,6 +49,7 @@ pub struct UserDisplay {
     pub win: Option<i32>,
     pub lose: Option<i32>,
     pub is_online: Option<bool>,
+    pub is_bot: bool,
 }
 
 impl User {
@@ -49,6 +72,7 @@ impl User {
             win: self.win,
             lose: self.lose,
             is_online: None,
+            is_bot: self.is_bot,
         }
     }
 }
@@ -8,7 +8,8 @@ use crate::auth::Auth;
 use crate::config::{get_conn, AppState, DbPool};
 use crate::db::{channel, user};
 use crate::error::{Res, ResBody};
-use crate::models::user::UserDisplay;
+use crate::models::user::{RolePreference, UserDisplay};
+use crate::ws::game::characters::roles;
 
 #[derive(Deserialize)]
 pub struct NewUser {
@@ -175,3 +176,68 @@ pub async fn send_msg(
 
     ResBody::new("ok".to_string(), msg)
 }
+
+#[get("/prefs/")]
+pub async fn get_prefs(auth: Auth, pool: web::Data<DbPool>) -> Res {
+    let prefs = web::block(move || {
+        let conn = get_conn(pool);
+        user::get_role_prefs(&conn, auth.user_id)
+    })
+    .await?;
+
+    ResBody::new("ok".to_string(), prefs)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewPrefs {
+    preferred: Vec<String>,
+    avoided: Vec<String>,
+}
+
+#[post("/prefs/")]
+pub async fn set_prefs(
+    auth: Auth,
+    pool: web::Data<DbPool>,
+    new_prefs: web::Json<NewPrefs>,
+) -> Res {
+    let parse = |names: &Vec<String>| {
+        let mut list: Vec<String> = vec![];
+        for name in names {
+            let role = roles::parse(name).ok_or(ErrorUnprocessableEntity(
+                format!("role {} not found", name),
+            ))?;
+            if list.iter().any(|r| r == role) {
+                return Err(ErrorUnprocessableEntity(format!(
+                    "role {} is listed twice",
+                    role
+                )));
+            }
+            list.push(role.to_string());
+        }
+        Ok(list)
+    };
+    let preferred = parse(&new_prefs.preferred)?;
+    let avoided = parse(&new_prefs.avoided)?;
+
+    if let Some(role) = preferred.iter().find(|r| avoided.contains(r)) {
+        return Err(ErrorUnprocessableEntity(format!(
+            "role {} is both preferred and avoided",
+            role
+        )));
+    }
+
+    let prefs = web::block(move || {
+        let conn = get_conn(pool);
+        user::set_role_prefs(
+            &conn,
+            &RolePreference {
+                user_id: auth.user_id,
+                preferred,
+                avoided,
+            },
+        )
+    })
+    .await?;
+
+    ResBody::new("ok".to_string(), prefs)
+}
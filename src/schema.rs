@@ -34,6 +34,31 @@ table! {
 table! {
     games (id) {
         id -> Int8,
+        name -> Text,
+        settings -> Text,
+        host_id -> Nullable<Int8>,
+        is_private -> Bool,
+        phase_deadline -> Nullable<Int8>,
+        seed -> Nullable<Int8>,
+        snapshot -> Nullable<Text>,
+    }
+}
+
+table! {
+    mode_stats (user_id, mode, version) {
+        user_id -> Int8,
+        mode -> Text,
+        version -> Int4,
+        win -> Int4,
+        lose -> Int4,
+    }
+}
+
+table! {
+    role_preferences (user_id) {
+        user_id -> Int8,
+        preferred -> Array<Text>,
+        avoided -> Array<Text>,
     }
 }
 
@@ -56,6 +81,7 @@ table! {
         avatar_url -> Nullable<Text>,
         win -> Nullable<Int4>,
         lose -> Nullable<Int4>,
+        is_bot -> Bool,
     }
 }
 
@@ -65,6 +91,8 @@ joinable!(game_channels -> channels (channel_id));
 joinable!(game_channels -> games (game_id));
 joinable!(game_users -> games (game_id));
 joinable!(game_users -> users (user_id));
+joinable!(mode_stats -> users (user_id));
+joinable!(role_preferences -> users (user_id));
 joinable!(user_channel_permissions -> channels (channel_id));
 joinable!(user_channel_permissions -> users (user_id));
 
@@ -74,6 +102,8 @@ allow_tables_to_appear_in_same_query!(
     game_channels,
     game_users,
     games,
+    mode_stats,
+    role_preferences,
     user_channel_permissions,
     users,
 );
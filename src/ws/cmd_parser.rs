@@ -4,9 +4,10 @@ use serde::{Deserialize, Serialize};
 use serde_json::{from_str, to_string};
 
 use crate::models::channel::{ChannelPermission, DispChatMsg};
+use crate::models::game::GameDisplay;
 use crate::models::user::UserDisplay;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Cmd {
     SendReq {
         channel_id: String,
@@ -44,6 +45,13 @@ pub enum Cmd {
         channel_id: Option<String>,
     },
     GetPersRes(HashMap<String, ChannelPermission>),
+    ListGames,
+    ListGamesRes(Vec<GameDisplay>),
+    GameInvite {
+        game_id: String,
+        code: String,
+        from: String,
+    },
     UserOnline(UserDisplay),
     UserOffline(UserDisplay),
     GameEvent(GameEvent),
@@ -56,19 +64,71 @@ pub enum GameEvent {
     LeaveGame(String),
     UserJoin(String),
     UserLeave(String),
+    UserKicked(String),
+    SpectateGame(String),
+    UnspectateGame(String),
+    SpectatorJoin(String),
+    SpectatorLeave(String),
+    NewHost(Option<String>),
+    NewNarrator(Option<String>),
     UserStart(String),
     UserStop(String),
     UserNext(String),
     UserVote { user_id: String, vote_for: String },
-    PlayerDied(String),
+    UserUnvote(String),
+    VoteTally(HashMap<String, u16>),
+    VoteRecord(u16, HashMap<String, String>),
+    NightResult(String, NightResult),
+    GameHistory(Vec<GameEvent>),
+    GameMode(Option<(String, u32)>),
+    // seed of the game rng, enough to replay the deal, sent when it ends
+    Seed(String),
+    // user id, cause of death and the role if it is revealed
+    PlayerDied(String, DeathCause, Option<String>),
     PlayerReborn(String),
+    PlayerAway(String),
+    PlayerBack(String),
+    PlayerAfk(String),
+    // old and new user id of a substituted seat
+    PlayerSubstituted(String, String),
+    Seats(Vec<String>),
     NewPhase { num_day: u16, is_day: bool },
+    // remaining and total seconds of the auto-start countdown
+    AutoStart(u64, u64),
+    AutoStartCancel,
+    // remaining and total seconds of the phase and whether it is paused
+    Timer(u64, u64, bool),
     StartGame,
+    // user id to name and avatar of an anonymous game, each player finds
+    // their own under their real id
+    Pseudonyms(HashMap<String, (String, String)>),
     EndGame { winner: String },
+    // pseudonym id to user id of an anonymous game
+    Identities(HashMap<String, String>),
     StopGame,
     StopGame_(Vec<i64>),
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeathCause {
+    Lynched,
+    Wolves,
+    Curse,
+    Grief,
+    Vision,
+    Forest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum NightResult {
+    GuardSaved(String),
+    GuardQuiet(String),
+    WolfKilled(String),
+    WolfBlocked(String),
+    CurseLanded(String),
+    CurseBlocked(String),
+}
+
 impl Cmd {
     pub fn from_string(string: &str) -> serde_json::Result<Self> {
         from_str::<Self>(string)
@@ -0,0 +1,124 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use super::characters::roles;
+use super::game::{GameChannel, GameInfo};
+
+// what a bot does this phase: the channel to type in and the command,
+// without the bot prefix
+pub fn plan(
+    info: &mut GameInfo,
+    bot: i64,
+    wolf_target: &mut Option<i64>,
+    rng: &mut StdRng,
+) -> Option<(GameChannel, String)> {
+    let (mut alive, _) = info.get_alives();
+    alive.sort();
+    if !alive.contains(&bot) {
+        return None;
+    }
+    let role = info.players.get(&bot)?.get_role_name();
+    let others = alive
+        .iter()
+        .filter(|&&uid| uid != bot)
+        .cloned()
+        .collect::<Vec<i64>>();
+    let notes = info.bot_notes.get(&bot).cloned().unwrap_or_default();
+
+    if info.is_day {
+        let mut candidates = if roles::is_wolf(role) {
+            // never vote out a pack mate
+            others
+                .iter()
+                .filter(|uid| !is_wolf(info, uid))
+                .cloned()
+                .collect::<Vec<i64>>()
+        } else {
+            // the seer goes after what it saw and spares who it cleared
+            let wolves = others
+                .iter()
+                .filter(|uid| notes.get(uid) == Some(&true))
+                .cloned()
+                .collect::<Vec<i64>>();
+            if !wolves.is_empty() {
+                wolves
+            } else {
+                others
+                    .iter()
+                    .filter(|uid| notes.get(uid) != Some(&false))
+                    .cloned()
+                    .collect()
+            }
+        };
+        if candidates.is_empty() {
+            candidates = others;
+        }
+        // follow the crowd when it points at someone acceptable
+        let target = top_vote(info)
+            .filter(|uid| candidates.contains(uid))
+            .or_else(|| candidates.choose(rng).cloned())?;
+        return Some((GameChannel::GamePlay, format!("vote <@{}>", target)));
+    }
+
+    let personal = GameChannel::Personal(bot);
+    match role {
+        roles::WEREWOLF | roles::SUPERWOLF => {
+            if wolf_target.is_none() {
+                let prey = others
+                    .iter()
+                    .filter(|uid| !is_wolf(info, uid))
+                    .cloned()
+                    .collect::<Vec<i64>>();
+                *wolf_target = prey.choose(rng).cloned();
+            }
+            let target = (*wolf_target)?;
+            Some((GameChannel::WereWolf, format!("kill <@{}>", target)))
+        }
+        roles::GUARD => {
+            let yesterday = info.guard_yesterday_target.map(|(uid, _)| uid);
+            let target = alive
+                .iter()
+                .filter(|&&uid| Some(uid) != yesterday)
+                .cloned()
+                .collect::<Vec<i64>>()
+                .choose(rng)
+                .cloned()?;
+            Some((personal, format!("guard <@{}>", target)))
+        }
+        roles::SEER => {
+            let target = others
+                .iter()
+                .filter(|uid| !notes.contains_key(uid))
+                .cloned()
+                .collect::<Vec<i64>>()
+                .choose(rng)
+                .cloned()?;
+            Some((personal, format!("seer <@{}>", target)))
+        }
+        roles::CUPID if info.players.get_mut(&bot)?.get_power() => {
+            let pair =
+                alive.choose_multiple(rng, 2).cloned().collect::<Vec<i64>>();
+            if pair.len() < 2 {
+                return None;
+            }
+            Some((personal, format!("ship <@{}> <@{}>", pair[0], pair[1])))
+        }
+        _ => None,
+    }
+}
+
+fn is_wolf(info: &GameInfo, uid: &i64) -> bool {
+    roles::is_wolf(info.players.get(uid).unwrap().get_role_name())
+}
+
+fn top_vote(info: &GameInfo) -> Option<i64> {
+    let mut tally = std::collections::HashMap::new();
+    for &uid in info.vote_kill.values() {
+        *tally.entry(uid).or_insert(0) += 1;
+    }
+    // the lowest id wins a tie so replays agree
+    tally
+        .into_iter()
+        .max_by_key(|&(uid, n)| (n, -uid))
+        .map(|(uid, _)| uid)
+}
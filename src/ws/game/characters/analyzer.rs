@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use super::super::settings::{MAX_PLAYERS, MIN_PLAYERS};
+use super::{draw_config, roles, RoleConfig, FRR};
+
+// what the random draw deals on average for one table size
+pub struct SizeReport {
+    pub size: usize,
+    pub expected: BTreeMap<String, f64>,
+    pub wolf_ratio: f64,
+    // share of the draws without wolves, with half the table or more being
+    // wolves, and that failed outright
+    pub no_wolf: f64,
+    pub wolf_half: f64,
+    pub failed: f64,
+}
+
+// problems that break a game of some size, empty when the config is sound,
+// `samples` draws per size must all succeed
+pub fn validate(config: &RoleConfig, samples: usize) -> Vec<String> {
+    let mut errors = vec![];
+
+    for size in MIN_PLAYERS..=MAX_PLAYERS {
+        let rules = match config.get(&size) {
+            Some(rules) => rules,
+            None => {
+                errors.push(format!("{} players: no entry", size));
+                continue;
+            }
+        };
+
+        let (mut fixed, mut most, mut wolves) = (0, 0, 0);
+        let mut names = rules.keys().collect::<Vec<&String>>();
+        names.sort();
+        for role in names {
+            if roles::parse(role) != Some(role.as_str()) {
+                errors.push(format!("{} players: unknown role {}", size, role));
+            }
+            let max = match rules[role] {
+                FRR::Fixed(n) => {
+                    fixed += n;
+                    n
+                }
+                FRR::Range(a, b) => {
+                    if a > b {
+                        errors.push(format!(
+                            "{} players: {} range {}-{} is empty",
+                            size, role, a, b
+                        ));
+                    }
+                    b
+                }
+                FRR::Rate(rate, max) => {
+                    if rate <= 0.0 || rate > 1.0 {
+                        errors.push(format!(
+                            "{} players: {} rate {} is outside (0, 1]",
+                            size, role, rate
+                        ));
+                    }
+                    max
+                }
+            };
+            most += max;
+            if roles::is_wolf(role) {
+                wolves += max;
+            }
+        }
+
+        if fixed > size {
+            errors.push(format!(
+                "{} players: {} fixed roles don't fit",
+                size, fixed
+            ));
+        }
+        if most < size {
+            errors.push(format!(
+                "{} players: at most {} roles can be drawn",
+                size, most
+            ));
+        }
+        if wolves == 0 {
+            errors.push(format!("{} players: no wolf can be drawn", size));
+        }
+    }
+
+    for report in analyze(config, samples, 0) {
+        if report.failed > 0.0 {
+            errors.push(format!(
+                "{} players: {:.1}% of the draws fail",
+                report.size,
+                report.failed * 100.0
+            ));
+        }
+    }
+
+    errors
+}
+
+// Monte-Carlo run of `samples` draws per table size, seeded for repeatable
+// numbers
+pub fn analyze(
+    config: &RoleConfig,
+    samples: usize,
+    seed: u64,
+) -> Vec<SizeReport> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let weights = HashMap::new();
+    let mut sizes = config.keys().cloned().collect::<Vec<usize>>();
+    sizes.sort();
+
+    sizes
+        .into_iter()
+        .map(|size| {
+            let rules = &config[&size];
+            let mut totals = BTreeMap::new();
+            let (mut ratio, mut no_wolf, mut wolf_half, mut failed) =
+                (0.0, 0, 0, 0);
+
+            for _ in 0..samples {
+                let rls = match draw_config(size, rules, &weights, &mut rng) {
+                    Ok(rls) => rls,
+                    Err(_) => {
+                        failed += 1;
+                        continue;
+                    }
+                };
+                let mut wolves = 0;
+                for (role, num) in rls {
+                    if roles::is_wolf(&role) {
+                        wolves += num;
+                    }
+                    *totals.entry(role).or_insert(0) += num;
+                }
+                ratio += wolves as f64 / size as f64;
+                no_wolf += (wolves == 0) as usize;
+                wolf_half += (wolves * 2 >= size) as usize;
+            }
+
+            let ok = (samples - failed).max(1) as f64;
+            SizeReport {
+                size,
+                expected: totals
+                    .into_iter()
+                    .map(|(role, n)| (role, n as f64 / ok))
+                    .collect(),
+                wolf_ratio: ratio / ok,
+                no_wolf: no_wolf as f64 / ok,
+                wolf_half: wolf_half as f64 / ok,
+                failed: failed as f64 / samples.max(1) as f64,
+            }
+        })
+        .collect()
+}
+
+impl std::fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} players: wolves {:.1}%, no wolf {:.1}%, wolf half {:.1}%, failed {:.1}%",
+            self.size,
+            self.wolf_ratio * 100.0,
+            self.no_wolf * 100.0,
+            self.wolf_half * 100.0,
+            self.failed * 100.0
+        )?;
+        for (role, n) in self.expected.iter() {
+            writeln!(f, "    {:<10} {:.2}", role, n)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::fixtures::{config, rng, table};
+    use super::*;
+
+    fn sound() -> Vec<(&'static str, FRR)> {
+        vec![
+            (roles::WEREWOLF, FRR::Fixed(1)),
+            (roles::SEER, FRR::Range(0, 1)),
+            (roles::VILLAGER, FRR::Rate(1.0, MAX_PLAYERS)),
+        ]
+    }
+
+    #[test]
+    fn draw_config_fills_every_seat() {
+        let mut rng = rng(0);
+        let rules = table(&sound());
+        for size in MIN_PLAYERS..=MAX_PLAYERS {
+            let rls =
+                draw_config(size, &rules, &HashMap::new(), &mut rng).unwrap();
+            assert_eq!(rls.values().sum::<usize>(), size);
+        }
+    }
+
+    #[test]
+    fn draw_config_rejects_bad_tables() {
+        let mut rng = rng(0);
+        let weights = HashMap::new();
+        let too_many = table(&[(roles::WEREWOLF, FRR::Fixed(5))]);
+        assert!(draw_config(4, &too_many, &weights, &mut rng).is_err());
+        let empty = table(&[(roles::WEREWOLF, FRR::Range(2, 1))]);
+        assert!(draw_config(4, &empty, &weights, &mut rng).is_err());
+        let short = table(&[(roles::WEREWOLF, FRR::Rate(1.0, 2))]);
+        assert!(draw_config(4, &short, &weights, &mut rng).is_err());
+    }
+
+    #[test]
+    fn validate_accepts_a_sound_config() {
+        assert!(validate(&config(&sound()), 100).is_empty());
+    }
+
+    #[test]
+    fn validate_reports_bad_configs() {
+        let mut missing = config(&sound());
+        missing.remove(&MIN_PLAYERS);
+        assert!(!validate(&missing, 100).is_empty());
+
+        let no_wolf = config(&[(roles::VILLAGER, FRR::Rate(1.0, MAX_PLAYERS))]);
+        assert!(!validate(&no_wolf, 100).is_empty());
+
+        let unknown = config(&[
+            (roles::WEREWOLF, FRR::Fixed(1)),
+            ("Dragon", FRR::Rate(1.0, MAX_PLAYERS)),
+        ]);
+        assert!(!validate(&unknown, 100).is_empty());
+
+        // enough roles on paper, but a range can eat the seats a fixed
+        // count still needs
+        let short = config(&[
+            (roles::WEREWOLF, FRR::Range(1, MAX_PLAYERS)),
+            (roles::VILLAGER, FRR::Fixed(2)),
+        ]);
+        assert!(validate(&short, 100)
+            .iter()
+            .any(|err| err.contains("of the draws fail")));
+    }
+}
@@ -4,10 +4,15 @@ use actix::Addr;
 use rand::{prelude::SliceRandom, Rng};
 use serde::{Deserialize, Serialize};
 
+use crate::models::user::RolePreference;
 use crate::ws::ChatServer;
 
+use super::settings::GameSettings;
+use super::text_templates as ttp;
+
 use self::player::Player;
 
+pub mod analyzer;
 pub mod bettrayer;
 pub mod cupid;
 pub mod fox;
@@ -31,9 +36,27 @@ pub mod roles {
     pub const WITCH: &'static str = "Witch";
     pub const CUPID: &'static str = "Cupid";
     pub const BETRAYER: &'static str = "Betrayer";
+
+    pub const ALL: [&str; 10] = [
+        VILLAGER, WEREWOLF, SUPERWOLF, SEER, GUARD, LYCAN, FOX, WITCH, CUPID,
+        BETRAYER,
+    ];
+
+    // roles that may be dealt more than once in a custom deck
+    pub const STACKABLE: [&str; 3] = [VILLAGER, WEREWOLF, LYCAN];
+
+    pub fn parse(name: &str) -> Option<&'static str> {
+        ALL.iter()
+            .find(|role| role.eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    pub fn is_wolf(role: &str) -> bool {
+        role == WEREWOLF || role == SUPERWOLF
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(untagged)]
 pub enum FRR {
     Fixed(usize),
@@ -41,44 +64,196 @@ pub enum FRR {
     Rate(f32, usize),
 }
 
-pub type RoleConfig<'a> = HashMap<usize, HashMap<&'a str, FRR>>;
+pub type RoleConfig = HashMap<usize, HashMap<String, FRR>>;
+
+// chance that a player's preferences get a say in the deal at all
+pub const PREF_CHANCE: f64 = 0.5;
+
+pub const ROLE_CONFIG: &str = "./jsons/role-config.json";
 
-pub fn rand_roles(
+// the deal only depends on `rng`, so sets and maps are walked in sorted
+// order to replay a game from its seed
+pub fn rand_roles<R: Rng>(
     uids: &Vec<&i64>,
+    settings: &GameSettings,
+    prefs: &[RolePreference],
+    rng: &mut R,
     addr: Addr<ChatServer>,
 ) -> Result<HashMap<i64, Box<dyn Player>>, String> {
-    let json = read_to_string("./jsons/role-config.json")
-        .map_err(|err| err.to_string())?;
-    let config = serde_json::from_str::<RoleConfig>(&json).unwrap();
+    let mut rs = HashMap::new();
+    for (id, role) in deal_cards(uids, settings, prefs, rng)? {
+        rs.insert(id, new_role(&role, id, addr.clone())?);
+    }
+
+    Ok(rs)
+}
 
-    let roles = config.get(&uids.len()).unwrap();
+// user id and role name of every card of the deal
+fn deal_cards<R: Rng>(
+    uids: &[&i64],
+    settings: &GameSettings,
+    prefs: &[RolePreference],
+    rng: &mut R,
+) -> Result<Vec<(i64, String)>, String> {
+    let rls = if settings.deck.is_empty() {
+        rand_config(uids.len(), &settings.weights, rng)?
+    } else {
+        deck_config(uids.len(), &settings.deck)
+    };
+    let mut rls = rls.into_iter().collect::<Vec<(String, usize)>>();
+    rls.sort();
 
-    let mut num = uids.len();
+    let mut uids = uids.to_vec();
+    uids.sort();
+    uids.shuffle(rng);
+
+    let mut cards = vec![];
+    for (role, num) in rls.iter() {
+        for _ in 0..*num {
+            let &id = uids.pop().ok_or("pop false".to_string())?;
+            cards.push((id, role.as_str()));
+        }
+    }
+    apply_prefs(&mut cards, prefs, rng);
+
+    Ok(cards
+        .into_iter()
+        .map(|(id, role)| (id, role.to_string()))
+        .collect())
+}
+
+// how happy a user is with a role, a ranked preference beats no opinion
+// and an avoided role is the worst
+fn pref_score(prefs: Option<&RolePreference>, role: &str) -> i32 {
+    let prefs = match prefs {
+        Some(prefs) => prefs,
+        None => return 0,
+    };
+    if let Some(rank) = prefs.preferred.iter().position(|r| r == role) {
+        return (roles::ALL.len() - rank) as i32;
+    }
+    if prefs.avoided.iter().any(|r| r == role) {
+        return -(roles::ALL.len() as i32);
+    }
+    0
+}
+
+// preferences only weight the random deal: with PREF_CHANCE a player
+// trades their card for the one that makes the pair happiest, so the role
+// counts never change and nobody can count on a role
+fn apply_prefs<R: Rng>(
+    cards: &mut [(i64, &str)],
+    prefs: &[RolePreference],
+    rng: &mut R,
+) {
+    let get = |id: i64| prefs.iter().find(|p| p.user_id == id);
+    let gain = |(a, ra): (i64, &str), (b, rb): (i64, &str)| {
+        pref_score(get(a), rb) + pref_score(get(b), ra)
+            - pref_score(get(a), ra)
+            - pref_score(get(b), rb)
+    };
+
+    for i in 0..cards.len() {
+        // drawn for everyone so the rng use doesn't depend on preferences
+        if !rng.gen_bool(PREF_CHANCE) || get(cards[i].0).is_none() {
+            continue;
+        }
+        let mut best = None;
+        for j in 0..cards.len() {
+            let g = gain(cards[i], cards[j]);
+            if g > best.map_or(0, |(_, g)| g) {
+                best = Some((j, g));
+            }
+        }
+        if let Some((j, _)) = best {
+            let (ri, rj) = (cards[i].1, cards[j].1);
+            cards[i].1 = rj;
+            cards[j].1 = ri;
+        }
+    }
+}
+
+// missing cards of a custom deck are filled with villagers
+fn deck_config(num: usize, deck: &Vec<String>) -> HashMap<String, usize> {
     let mut rls = HashMap::new();
-    for (&role, frr) in roles {
+    for role in deck {
+        *rls.entry(role.clone()).or_default() += 1;
+    }
+    *rls.entry(roles::VILLAGER.to_string()).or_default() += num - deck.len();
+    rls
+}
+
+// weights scale the rate of rate-based roles, e.g. for the chaos mode
+fn rand_config<R: Rng>(
+    num: usize,
+    weights: &HashMap<String, f32>,
+    rng: &mut R,
+) -> Result<HashMap<String, usize>, String> {
+    let config = load_config(ROLE_CONFIG)?;
+    let roles = config.get(&num).ok_or(ttp::role_config_missing(num))?;
+    draw_config(num, roles, weights, rng)
+        .map_err(|_| ttp::role_config_broken(num))
+}
+
+pub fn load_config(path: &str) -> Result<RoleConfig, String> {
+    let json = read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str::<RoleConfig>(&json).map_err(|err| err.to_string())
+}
+
+// one draw of the `num` seats table, roles are walked in name order so the
+// result only depends on `rng`
+pub fn draw_config<R: Rng>(
+    num: usize,
+    roles: &HashMap<String, FRR>,
+    weights: &HashMap<String, f32>,
+    rng: &mut R,
+) -> Result<HashMap<String, usize>, String> {
+    let mut roles = roles.iter().collect::<Vec<(&String, &FRR)>>();
+    roles.sort_by_key(|&(role, _)| role);
+
+    let mut num = num;
+    let mut rls = HashMap::new();
+    for &(role, frr) in roles.iter() {
         if let FRR::Fixed(n) = frr {
-            rls.insert(role, *n);
-            num -= n;
+            num = num
+                .checked_sub(*n)
+                .ok_or(format!("fixed roles need more than {} seats", num))?;
+            rls.insert(role.clone(), *n);
         }
     }
-    for (&role, frr) in roles {
+    for &(role, frr) in roles.iter() {
         if let FRR::Range(a, b) = frr {
-            let r = rand::thread_rng().gen_range(*a..(*b + 1));
+            if a > b {
+                return Err(format!("{} has an empty range", role));
+            }
+            let r = rng.gen_range(*a..(*b + 1));
             if num < r {
                 continue;
             }
-            rls.insert(role, r);
+            rls.insert(role.clone(), r);
             num -= r;
         }
     }
-    'outer: loop {
-        for (&role, frr) in roles {
+    while num > 0 {
+        // every rate role is full, the loop would never end
+        let open = roles.iter().any(|&(role, frr)| match frr {
+            FRR::Rate(rate, max) => {
+                *rate > 0.0 && rls.get(role).unwrap_or(&0) < max
+            }
+            _ => false,
+        });
+        if !open {
+            return Err(format!("{} seats left without a role", num));
+        }
+
+        for &(role, frr) in roles.iter() {
             if num == 0 {
-                break 'outer;
+                break;
             }
             if let FRR::Rate(rate, max) = frr {
-                if rand::thread_rng().gen::<f32>() < *rate {
-                    let r = rls.entry(role).or_default();
+                let rate = rate * weights.get(role).unwrap_or(&1.0);
+                if rng.gen::<f32>() < rate {
+                    let r = rls.entry(role.clone()).or_default();
                     if *r >= *max {
                         continue;
                     }
@@ -89,22 +264,10 @@ pub fn rand_roles(
         }
     }
 
-    let mut uids = uids.clone();
-    uids.shuffle(&mut rand::thread_rng());
-
-    let mut rs = HashMap::new();
-    for (&role, &num) in rls.iter() {
-        for _ in 0..num {
-            let &id = uids.pop().ok_or("pop false".to_string())?;
-            let role = new_role(role, id, addr.clone())?;
-            rs.insert(id, role);
-        }
-    }
-
-    Ok(rs)
+    Ok(rls)
 }
 
-fn new_role(
+pub fn new_role(
     role: &str,
     id: i64,
     addr: Addr<ChatServer>,
@@ -123,3 +286,68 @@ fn new_role(
         _ => Err(format!("not found role {}", role)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::{pref, rng, seats, DEAL};
+    use super::*;
+
+    fn counts(cards: &[(i64, &str)]) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for (_, role) in cards {
+            *counts.entry(role.to_string()).or_default() += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn apply_prefs_keeps_role_counts() {
+        let prefs = vec![
+            pref(1, &[roles::SEER, roles::WITCH], &[roles::WEREWOLF]),
+            pref(3, &[roles::WEREWOLF], &[]),
+            pref(4, &[roles::SEER], &[roles::VILLAGER]),
+            pref(6, &[], &[roles::WITCH]),
+        ];
+        for seed in 0..100 {
+            let mut cards = DEAL;
+            apply_prefs(&mut cards, &prefs, &mut rng(seed));
+            assert_eq!(counts(&cards), counts(&DEAL));
+        }
+    }
+
+    #[test]
+    fn apply_prefs_without_prefs_keeps_the_deal() {
+        let mut cards = DEAL;
+        apply_prefs(&mut cards, &[], &mut rng(0));
+        assert_eq!(cards, DEAL);
+    }
+
+    #[test]
+    fn same_seed_deals_the_same() {
+        let ids = seats(8);
+        let uids = ids.iter().collect::<Vec<&i64>>();
+        let settings = GameSettings::default();
+        let prefs = vec![pref(2, &[roles::SEER], &[roles::WEREWOLF])];
+        let deal = |seed| {
+            deal_cards(&uids, &settings, &prefs, &mut rng(seed)).unwrap()
+        };
+
+        for seed in 0..20 {
+            assert_eq!(deal(seed), deal(seed));
+        }
+        assert!((0..20).any(|seed| deal(seed) != deal(seed + 1)));
+    }
+
+    #[test]
+    fn apply_prefs_weights_without_deciding() {
+        let prefs = vec![pref(3, &[roles::SEER], &[])];
+        let seers = (0..200)
+            .filter(|&seed| {
+                let mut cards = DEAL;
+                apply_prefs(&mut cards, &prefs, &mut rng(seed));
+                cards.contains(&(3, roles::SEER))
+            })
+            .count();
+        assert!(seers > 0 && seers < 200);
+    }
+}
@@ -1,4 +1,5 @@
 use actix::Addr;
+use serde::{Deserialize, Serialize};
 
 use crate::ws::{
     game::{cmds::BotMsg, text_templates as ttp},
@@ -7,7 +8,7 @@ use crate::ws::{
 
 use super::roles;
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum PlayerStatus {
     Alive,
     Killed,
@@ -1,10 +1,13 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
-use actix::{Handler, Message};
+use actix::{Arbiter, AsyncContext, Context, Handler, Message};
+use rand::Rng;
 
-use crate::ws::cmd_parser::GameEvent;
+use crate::ws::cmd_parser::{DeathCause, GameEvent};
 
 use super::characters::roles;
+use super::settings::{is_enough_vote, AfkPolicy, ModeConfig};
 use super::text_templates as ttp;
 use super::{game::GameChannel, Game};
 
@@ -22,14 +25,86 @@ pub struct Leave {
     pub msg_id: i64,
 }
 
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Spectate {
+    pub user_id: i64,
+    pub msg_id: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Unspectate {
+    pub user_id: i64,
+    pub msg_id: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Sub {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub target: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Approve {
+    pub user_id: i64,
+    pub target: Result<i64, u16>,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
 pub struct Start {
     pub user_id: i64,
     pub msg_id: i64,
     pub channel_id: i64,
+    pub force: bool,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Kick {
+    pub user_id: i64,
+    pub target: Result<i64, u16>,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Invite {
+    pub user_id: i64,
+    pub target: Result<i64, u16>,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Host {
+    pub user_id: i64,
+    pub target: Result<i64, u16>,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct CancelStart {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
 }
 
+// fired by the auto-start countdown, carries its generation
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct AutoStart(pub u64);
+
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
 pub struct Stop {
@@ -46,6 +121,113 @@ pub struct Next {
     pub channel_id: i64,
 }
 
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Pause {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
+    pub resume: bool,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Extend {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
+    pub secs: u64,
+}
+
+// a bot typing `message` into a channel, handled by the chat server
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct BotPlay {
+    pub user_id: i64,
+    pub channel_id: i64,
+    pub message: String,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct AddBot {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Narrator {
+    pub user_id: i64,
+    pub target: Option<Result<i64, u16>>,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Advance {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Override {
+    pub user_id: i64,
+    pub target: Result<i64, u16>,
+    pub kill: bool,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Set {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Mode {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
+    pub name: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum DeckCmd {
+    Show,
+    Add(String),
+    Remove(String),
+    Preset(String),
+    Clear,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Roles {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
+    pub cmd: DeckCmd,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Settings {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
 pub struct Vote {
@@ -55,6 +237,22 @@ pub struct Vote {
     pub channel_id: i64,
 }
 
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Unvote {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Votes {
+    pub user_id: i64,
+    pub msg_id: i64,
+    pub channel_id: i64,
+}
+
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
 pub struct Kill {
@@ -110,6 +308,21 @@ pub struct Curse {
     pub channel_id: i64,
 }
 
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Away {
+    pub user_id: i64,
+    pub is_away: bool,
+}
+
+// fired when the grace window of an away player runs out
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct AwayTimeout {
+    pub user_id: i64,
+    pub gen: u64,
+}
+
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
 pub struct BotMsg {
@@ -125,6 +338,15 @@ pub struct GameMsg {
     pub event: GameEvent,
 }
 
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct InviteMsg {
+    pub game_id: i64,
+    pub code: String,
+    pub from: i64,
+    pub to: i64,
+}
+
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
 pub struct UpdatePers(pub i64);
@@ -141,91 +363,467 @@ impl Game {
         };
         return true;
     }
-}
 
-impl Handler<Join> for Game {
-    type Result = ();
+    // lobby commands are only accepted before start, in lobby or gameplay
+    pub fn assert_lobby_cmd(
+        &self,
+        user_id: i64,
+        msg_id: i64,
+        channel_id: i64,
+    ) -> bool {
+        if !self.must_in_game(user_id, msg_id) {
+            return false;
+        }
 
-    fn handle(&mut self, msg: Join, _: &mut Self::Context) -> Self::Result {
-        if self.info.lock().unwrap().users.contains(&msg.user_id) {
-            return self.addr.do_send(BotMsg {
-                channel_id: 1,
-                msg: ttp::aready_in_game(),
-                reply_to: Some(msg.msg_id),
+        let info_lock = self.info.lock().unwrap();
+        let gameplay = *info_lock.channels.get(&GameChannel::GamePlay).unwrap();
+
+        let err = if channel_id != 1 && channel_id != gameplay {
+            ttp::must_in_channel(1)
+        } else if info_lock.is_started {
+            ttp::game_is_started()
+        } else {
+            return true;
+        };
+        drop(info_lock);
+
+        self.addr.do_send(BotMsg {
+            channel_id,
+            msg: err,
+            reply_to: Some(msg_id),
+        });
+        false
+    }
+
+    pub fn assert_host(
+        &self,
+        user_id: i64,
+        msg_id: i64,
+        channel_id: i64,
+    ) -> bool {
+        if self.info.lock().unwrap().host != Some(user_id) {
+            self.addr.do_send(BotMsg {
+                channel_id,
+                msg: ttp::not_host(),
+                reply_to: Some(msg_id),
             });
+            return false;
+        }
+        true
+    }
+
+    // lobby targets have no seat yet, so they must be mentioned
+    pub fn get_lobby_target(
+        &self,
+        target: Result<i64, u16>,
+    ) -> Result<i64, String> {
+        let target = target.map_err(|_| ttp::must_mention())?;
+        if !self.info.lock().unwrap().users.contains(&target) {
+            return Err(ttp::player_not_in_game(target));
         }
+        Ok(target)
+    }
 
-        if self.info.lock().unwrap().users.len() > 15 {
-            return self.addr.do_send(BotMsg {
-                channel_id: 1,
-                msg: ttp::max_player(),
-                reply_to: Some(msg.msg_id),
-            });
+    pub fn notify_host(&self, old_host: Option<i64>) {
+        let info_lock = self.info.lock().unwrap();
+        let host = info_lock.host;
+        let gameplay = *info_lock.channels.get(&GameChannel::GamePlay).unwrap();
+        drop(info_lock);
+
+        if host == old_host {
+            return;
         }
 
-        if let Err(err) = self.add_user(msg.user_id) {
+        if let Some(host) = host {
             self.addr.do_send(BotMsg {
-                channel_id: 1,
-                msg: err,
-                reply_to: Some(msg.msg_id),
-            })
+                channel_id: gameplay,
+                msg: ttp::new_host(host),
+                reply_to: None,
+            });
         }
-
-        self.addr.do_send(UpdatePers(msg.user_id));
-        self.addr.do_send(BotMsg {
-            channel_id: 1,
-            msg: ttp::user_join(
-                msg.user_id,
-                self.info.lock().unwrap().users.len(),
-            ),
-            reply_to: Some(msg.msg_id),
-        });
-        self.addr.do_send(BotMsg {
-            channel_id: *self
-                .info
-                .lock()
-                .unwrap()
-                .channels
-                .get(&GameChannel::GamePlay)
-                .unwrap(),
-            msg: format!("Hi <@{}>.", msg.user_id),
-            reply_to: None,
-        });
         self.addr.do_send(GameMsg {
             game_id: self.id,
-            event: GameEvent::UserJoin(msg.user_id.to_string()),
+            event: GameEvent::NewHost(host.map(|id| id.to_string())),
         });
     }
-}
 
-impl Handler<Leave> for Game {
-    type Result = ();
+    // a lobby left without people is closed, which frees its name
+    pub fn close_if_empty(&mut self) {
+        let info_lock = self.info.lock().unwrap();
+        let empty = !info_lock.is_started
+            && info_lock
+                .users
+                .iter()
+                .all(|uid| info_lock.bots.contains(uid));
+        let uids = info_lock
+            .users
+            .iter()
+            .chain(info_lock.narrator.iter())
+            .cloned()
+            .collect::<Vec<i64>>();
+        drop(info_lock);
 
-    fn handle(&mut self, msg: Leave, _: &mut Self::Context) -> Self::Result {
-        if !self.must_in_game(msg.user_id, msg.msg_id) {
+        if !empty || self.stop().is_err() {
             return;
         }
-
-        if self.info.lock().unwrap().is_started {
-            return self.addr.do_send(BotMsg {
-                channel_id: 1,
-                msg: ttp::leave_on_started(),
-                reply_to: Some(msg.msg_id),
-            });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::StopGame_(uids.clone()),
+        });
+        for user in uids {
+            self.addr.do_send(UpdatePers(user));
         }
+    }
 
-        if let Err(err) = self.remove_user(msg.user_id) {
-            self.addr.do_send(BotMsg {
-                channel_id: 1,
-                msg: err,
-                reply_to: Some(msg.msg_id),
-            })
-        }
+    pub fn start_game(&mut self, channel_id: i64, msg_id: Option<i64>) {
+        match self.start() {
+            Err(err) => {
+                return self.addr.do_send(BotMsg {
+                    channel_id,
+                    msg: err,
+                    reply_to: msg_id,
+                })
+            }
+            Ok(roles) => {
+                let info_lock = self.info.lock().unwrap();
+                if !info_lock.settings.hide_roles {
+                    self.addr.do_send(BotMsg {
+                        channel_id: *info_lock
+                            .channels
+                            .get(&GameChannel::GamePlay)
+                            .unwrap(),
+                        msg: ttp::roles_list(&roles),
+                        reply_to: None,
+                    })
+                }
+            }
+        }
+
+        self.cancel_auto_start();
+        self.addr.do_send(BotMsg {
+            channel_id: 1,
+            msg: ttp::start_game(),
+            reply_to: msg_id,
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::StartGame,
+        });
+
+        for &user in self.info.lock().unwrap().users.iter() {
+            self.addr.do_send(UpdatePers(user));
+        }
+    }
+
+    // (re)starts the countdown when the lobby is large enough, a running
+    // countdown is restarted when `restart` is set
+    pub fn check_auto_start(&self, ctx: &mut Context<Self>, restart: bool) {
+        let info_lock = self.info.lock().unwrap();
+        let target = info_lock.settings.auto_start;
+        // a deck that can't be dealt to this lobby never starts by itself
+        let enough = target > 0
+            && !info_lock.is_started
+            && info_lock.users.len() >= target
+            && info_lock.settings.check_deck(info_lock.users.len()).is_ok();
+        let counting = info_lock.auto_start.is_some();
+        drop(info_lock);
+
+        if counting && (restart || !enough) {
+            self.cancel_auto_start();
+        } else if counting {
+            return;
+        }
+        if !enough {
+            return;
+        }
+
+        let mut info_lock = self.info.lock().unwrap();
+        info_lock.auto_start_gen += 1;
+        let gen = info_lock.auto_start_gen;
+        info_lock.auto_start = Some(gen);
+        let timecount = info_lock.settings.auto_start_time;
+        let period = info_lock.settings.period;
+        let gameplay = *info_lock.channels.get(&GameChannel::GamePlay).unwrap();
+        drop(info_lock);
+
+        let addr = self.addr.clone();
+        let game = ctx.address();
+        let info = self.info.clone();
+        let game_id = self.id;
+
+        let fut = async move {
+            for count in (1..timecount + 1).rev() {
+                if info.lock().unwrap().auto_start != Some(gen) {
+                    return;
+                }
+
+                if count == timecount || count % period == 0 || count <= 5 {
+                    addr.do_send(BotMsg {
+                        channel_id: gameplay,
+                        msg: ttp::auto_start_timeout(count),
+                        reply_to: None,
+                    });
+                    addr.do_send(GameMsg {
+                        game_id,
+                        event: GameEvent::AutoStart(count, timecount),
+                    });
+                }
+
+                actix::clock::delay_for(Duration::from_secs(1)).await;
+            }
+
+            game.do_send(AutoStart(gen));
+        };
+
+        Arbiter::spawn(fut);
+    }
+
+    pub fn cancel_auto_start(&self) {
+        let mut info_lock = self.info.lock().unwrap();
+        if info_lock.auto_start.take().is_none() {
+            return;
+        }
+        let gameplay = *info_lock.channels.get(&GameChannel::GamePlay).unwrap();
+        let is_started = info_lock.is_started;
+        drop(info_lock);
+
+        if !is_started {
+            self.addr.do_send(BotMsg {
+                channel_id: gameplay,
+                msg: ttp::auto_start_cancelled(),
+                reply_to: None,
+            });
+        }
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::AutoStartCancel,
+        });
+    }
+
+    // wakes the loop when the next votes, afk players included, are enough
+    pub fn check_next(&self) -> bool {
+        let mut info_lock = self.info.lock().unwrap();
+        let numvote = info_lock.get_next_votes();
+        let numplayer = info_lock.users.len();
+        let percent = info_lock.settings.next_vote;
+        if !info_lock.is_started
+            || info_lock.is_ended
+            || info_lock.narrator.is_some()
+            || info_lock.timer.is_paused()
+            || !is_enough_vote(percent, numvote, numplayer)
+        {
+            return false;
+        }
+
+        info_lock.vote_nexts = HashSet::new();
+        let next = info_lock.next_flag.clone();
+        drop(info_lock);
+
+        next.wake();
+        true
+    }
+
+    // like assert_cmd_in, but still accepted while the game is paused
+    pub fn assert_running(
+        &self,
+        user_id: i64,
+        msg_id: i64,
+        channel_id: i64,
+    ) -> bool {
+        if !self.must_in_game(user_id, msg_id) {
+            return false;
+        }
+
+        let info_lock = self.info.lock().unwrap();
+        let gameplay = *info_lock.channels.get(&GameChannel::GamePlay).unwrap();
+        let err = if !info_lock.is_started {
+            ttp::game_is_not_started()
+        } else if info_lock.is_ended || info_lock.is_stopped {
+            ttp::stop_game()
+        } else if channel_id != gameplay {
+            ttp::must_in_channel(gameplay)
+        } else {
+            return true;
+        };
+        drop(info_lock);
+
+        self.addr.do_send(BotMsg {
+            channel_id,
+            msg: err,
+            reply_to: Some(msg_id),
+        });
+        false
+    }
+
+    pub fn assert_narrator(
+        &self,
+        user_id: i64,
+        msg_id: i64,
+        channel_id: i64,
+    ) -> bool {
+        let info_lock = self.info.lock().unwrap();
+        let err = if info_lock.narrator != Some(user_id) {
+            ttp::not_narrator()
+        } else if !info_lock.is_started {
+            ttp::game_is_not_started()
+        } else if info_lock.is_ended || info_lock.is_stopped {
+            ttp::stop_game()
+        } else {
+            return true;
+        };
+        drop(info_lock);
+
+        self.addr.do_send(BotMsg {
+            channel_id,
+            msg: err,
+            reply_to: Some(msg_id),
+        });
+        false
+    }
+
+    pub fn night_intent(&self, user_id: i64, action: &str, targets: &[i64]) {
+        let info_lock = self.info.lock().unwrap();
+        if info_lock.narrator.is_none() {
+            return;
+        }
+        if let Some(&narrator) = info_lock.channels.get(&GameChannel::Narrator)
+        {
+            self.addr.do_send(BotMsg {
+                channel_id: narrator,
+                msg: ttp::night_intent(user_id, action, targets),
+                reply_to: None,
+            });
+        }
+    }
+
+    pub fn send_timer(&self) {
+        let info_lock = self.info.lock().unwrap();
+        let event = GameEvent::Timer(
+            info_lock.timer.remaining_secs(),
+            info_lock.timer.total_secs(),
+            info_lock.timer.is_paused(),
+        );
+        drop(info_lock);
+
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event,
+        });
+    }
+
+    pub fn send_vote_tally(&self) {
+        let votes = self
+            .info
+            .lock()
+            .unwrap()
+            .get_vote_tally()
+            .iter()
+            .map(|(uid, num)| (uid.to_string(), *num))
+            .collect::<HashMap<String, u16>>();
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::VoteTally(votes),
+        });
+    }
+
+    pub fn done_night_action(&self, user_id: i64) {
+        let mut info_lock = self.info.lock().unwrap();
+        // with witch_both the witch may still use her other potion tonight
+        let witch_both =
+            info_lock.settings.witch_both && !info_lock.afk.contains(&user_id);
+        let witch_waits = witch_both
+            && info_lock.players.get_mut(&user_id).is_some_and(|player| {
+                player.get_role_name() == roles::WITCH
+                    && (player.get_power() || player.get_power2())
+            });
+        let dawn = !info_lock.is_day
+            && !witch_waits
+            && info_lock.night_pending_actions.remove(&user_id)
+            && info_lock.night_pending_actions.is_empty()
+            && info_lock.narrator.is_none();
+        let num_day = info_lock.num_day;
+        drop(info_lock);
+
+        self.save_snapshot();
+        if !dawn {
+            return;
+        }
+
+        // wait a random moment so an early dawn doesn't tell who acted last
+        let info = self.info.clone();
+        let delay = rand::thread_rng().gen_range(5..16);
+        let fut = async move {
+            actix::clock::delay_for(Duration::from_secs(delay)).await;
+
+            let lock = info.lock().unwrap();
+            if lock.is_ended
+                || lock.is_stopped
+                || lock.is_day
+                || lock.timer.is_paused()
+                || lock.num_day != num_day
+            {
+                return;
+            }
+            let next = lock.next_flag.clone();
+            drop(lock);
+
+            next.wake();
+        };
+
+        Arbiter::spawn(fut);
+    }
+}
+
+impl Handler<Join> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Join, ctx: &mut Self::Context) -> Self::Result {
+        if self.info.lock().unwrap().users.contains(&msg.user_id) {
+            return self.addr.do_send(BotMsg {
+                channel_id: 1,
+                msg: ttp::aready_in_game(),
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        if self.info.lock().unwrap().narrator == Some(msg.user_id) {
+            return self.addr.do_send(BotMsg {
+                channel_id: 1,
+                msg: ttp::is_narrator(),
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        if self.info.lock().unwrap().is_started {
+            return self.addr.do_send(BotMsg {
+                channel_id: 1,
+                msg: ttp::game_is_started(),
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        let max_players = self.info.lock().unwrap().settings.max_players;
+        if self.info.lock().unwrap().users.len() >= max_players {
+            return self.addr.do_send(BotMsg {
+                channel_id: 1,
+                msg: ttp::max_player(),
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        let old_host = self.info.lock().unwrap().host;
+        if let Err(err) = self.add_user(msg.user_id) {
+            self.addr.do_send(BotMsg {
+                channel_id: 1,
+                msg: err,
+                reply_to: Some(msg.msg_id),
+            })
+        }
 
         self.addr.do_send(UpdatePers(msg.user_id));
         self.addr.do_send(BotMsg {
             channel_id: 1,
-            msg: ttp::user_leave(
+            msg: ttp::user_join(
                 msg.user_id,
                 self.info.lock().unwrap().users.len(),
             ),
@@ -239,66 +837,503 @@ impl Handler<Leave> for Game {
                 .channels
                 .get(&GameChannel::GamePlay)
                 .unwrap(),
-            msg: format!("Bye <@{}>.", msg.user_id),
+            msg: format!("Hi <@{}>.", msg.user_id),
             reply_to: None,
         });
+        let info_lock = self.info.lock().unwrap();
+        if info_lock.is_private && info_lock.users.len() == 1 {
+            self.addr.do_send(BotMsg {
+                channel_id: *info_lock
+                    .channels
+                    .get(&GameChannel::GamePlay)
+                    .unwrap(),
+                msg: ttp::join_code(&self.bot_prefix, &self.name),
+                reply_to: None,
+            });
+        }
+        drop(info_lock);
         self.addr.do_send(GameMsg {
             game_id: self.id,
-            event: GameEvent::UserLeave(msg.user_id.to_string()),
+            event: GameEvent::UserJoin(msg.user_id.to_string()),
         });
+        self.notify_host(old_host);
+        self.check_auto_start(ctx, false);
     }
 }
 
-impl Handler<Start> for Game {
+impl Handler<Leave> for Game {
     type Result = ();
 
-    fn handle(&mut self, msg: Start, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: Leave, ctx: &mut Self::Context) -> Self::Result {
         if !self.must_in_game(msg.user_id, msg.msg_id) {
             return;
         }
 
-        let gameplay = *self
-            .info
-            .lock()
-            .unwrap()
-            .channels
-            .get(&GameChannel::GamePlay)
-            .unwrap();
-        let channel_id = msg.channel_id;
-
-        if channel_id != 1 && channel_id != gameplay {
+        if self.info.lock().unwrap().is_started {
             return self.addr.do_send(BotMsg {
-                channel_id,
-                msg: ttp::must_in_channel(1),
+                channel_id: 1,
+                msg: ttp::leave_on_started(),
                 reply_to: Some(msg.msg_id),
             });
         }
 
-        if self.info.lock().unwrap().is_started {
-            return self.addr.do_send(BotMsg {
-                channel_id,
-                msg: ttp::game_is_started(),
+        let old_host = self.info.lock().unwrap().host;
+        if let Err(err) = self.remove_user(msg.user_id) {
+            self.addr.do_send(BotMsg {
+                channel_id: 1,
+                msg: err,
                 reply_to: Some(msg.msg_id),
-            });
+            })
         }
 
-        let num_users = self.info.lock().unwrap().users.len();
-        if num_users < 4 {
+        self.addr.do_send(UpdatePers(msg.user_id));
+        self.addr.do_send(BotMsg {
+            channel_id: 1,
+            msg: ttp::user_leave(
+                msg.user_id,
+                self.info.lock().unwrap().users.len(),
+            ),
+            reply_to: Some(msg.msg_id),
+        });
+        self.addr.do_send(BotMsg {
+            channel_id: *self
+                .info
+                .lock()
+                .unwrap()
+                .channels
+                .get(&GameChannel::GamePlay)
+                .unwrap(),
+            msg: format!("Bye <@{}>.", msg.user_id),
+            reply_to: None,
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::UserLeave(msg.user_id.to_string()),
+        });
+        self.notify_host(old_host);
+        self.close_if_empty();
+        self.check_auto_start(ctx, true);
+    }
+}
+
+impl Handler<Kick> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Kick, ctx: &mut Self::Context) -> Self::Result {
+        if !self.assert_lobby_cmd(msg.user_id, msg.msg_id, msg.channel_id)
+            || !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+        {
+            return;
+        }
+
+        let target = match self.get_lobby_target(msg.target) {
+            Ok(target) if target == msg.user_id => Err(ttp::kick_yourself()),
+            target => target,
+        };
+        let target = match target {
+            Ok(target) => target,
+            Err(err) => {
+                return self.addr.do_send(BotMsg {
+                    channel_id: msg.channel_id,
+                    msg: err,
+                    reply_to: Some(msg.msg_id),
+                })
+            }
+        };
+
+        if let Err(err) = self.remove_user(target) {
             return self.addr.do_send(BotMsg {
-                channel_id,
-                msg: ttp::not_enough_player(num_users),
+                channel_id: msg.channel_id,
+                msg: err,
                 reply_to: Some(msg.msg_id),
             });
         }
 
-        self.info.lock().unwrap().vote_starts.insert(msg.user_id);
+        self.addr.do_send(UpdatePers(target));
+        self.addr.do_send(BotMsg {
+            channel_id: 1,
+            msg: ttp::user_kicked(
+                target,
+                self.info.lock().unwrap().users.len(),
+            ),
+            reply_to: Some(msg.msg_id),
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::UserKicked(target.to_string()),
+        });
+        self.close_if_empty();
+        self.check_auto_start(ctx, true);
+    }
+}
 
-        let numvote = self.info.lock().unwrap().vote_starts.len();
-        let numplayer = self.info.lock().unwrap().users.len();
-        if numvote * 3 < numplayer * 2 {
-            self.addr.do_send(GameMsg {
-                game_id: self.id,
-                event: GameEvent::UserStart(msg.user_id.to_string()),
+impl Handler<AddBot> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: AddBot, ctx: &mut Self::Context) -> Self::Result {
+        if !self.assert_lobby_cmd(msg.user_id, msg.msg_id, msg.channel_id)
+            || !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+        {
+            return;
+        }
+
+        let max_players = self.info.lock().unwrap().settings.max_players;
+        let res = if self.info.lock().unwrap().users.len() >= max_players {
+            Err(ttp::max_player())
+        } else {
+            self.add_free_bot()
+        };
+        let bot_id = match res {
+            Ok(bot_id) => bot_id,
+            Err(err) => {
+                return self.addr.do_send(BotMsg {
+                    channel_id: msg.channel_id,
+                    msg: err,
+                    reply_to: Some(msg.msg_id),
+                })
+            }
+        };
+
+        self.addr.do_send(BotMsg {
+            channel_id: msg.channel_id,
+            msg: ttp::bot_join(bot_id, self.info.lock().unwrap().users.len()),
+            reply_to: Some(msg.msg_id),
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::UserJoin(bot_id.to_string()),
+        });
+        self.check_auto_start(ctx, false);
+    }
+}
+
+impl Handler<Invite> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Invite, _: &mut Self::Context) -> Self::Result {
+        if !self.assert_lobby_cmd(msg.user_id, msg.msg_id, msg.channel_id) {
+            return;
+        }
+
+        // the code must not leak into the lobby channel
+        let gameplay = *self
+            .info
+            .lock()
+            .unwrap()
+            .channels
+            .get(&GameChannel::GamePlay)
+            .unwrap();
+        let target = match msg.target {
+            _ if msg.channel_id != gameplay => Err(ttp::must_in_gameplay()),
+            Ok(target) if self.info.lock().unwrap().users.contains(&target) => {
+                Err(ttp::player_in_game(target))
+            }
+            Ok(target) => Ok(target),
+            Err(_) => Err(ttp::must_mention()),
+        };
+        let target = match target {
+            Ok(target) => target,
+            Err(err) => {
+                return self.addr.do_send(BotMsg {
+                    channel_id: msg.channel_id,
+                    msg: err,
+                    reply_to: Some(msg.msg_id),
+                })
+            }
+        };
+
+        self.addr.do_send(InviteMsg {
+            game_id: self.id,
+            code: self.name.clone(),
+            from: msg.user_id,
+            to: target,
+        });
+        self.addr.do_send(BotMsg {
+            channel_id: msg.channel_id,
+            msg: ttp::user_invited(target),
+            reply_to: Some(msg.msg_id),
+        });
+    }
+}
+
+impl Handler<Host> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Host, _: &mut Self::Context) -> Self::Result {
+        if !self.assert_lobby_cmd(msg.user_id, msg.msg_id, msg.channel_id)
+            || !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+        {
+            return;
+        }
+
+        let res = self
+            .get_lobby_target(msg.target)
+            .and_then(|target| self.set_host(target));
+        if let Err(err) = res {
+            return self.addr.do_send(BotMsg {
+                channel_id: msg.channel_id,
+                msg: err,
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        self.notify_host(Some(msg.user_id));
+    }
+}
+
+impl Handler<Spectate> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Spectate, _: &mut Self::Context) -> Self::Result {
+        let info_lock = self.info.lock().unwrap();
+        let err = if info_lock.users.contains(&msg.user_id) {
+            Some(ttp::aready_in_game())
+        } else if info_lock.spectators.contains(&msg.user_id) {
+            Some(ttp::aready_spectating())
+        } else if info_lock.narrator == Some(msg.user_id) {
+            Some(ttp::is_narrator())
+        } else if !info_lock.is_started {
+            Some(ttp::game_is_not_started())
+        } else if info_lock.is_ended || info_lock.is_stopped {
+            Some(ttp::stop_game())
+        } else {
+            None
+        };
+        let gameplay = *info_lock.channels.get(&GameChannel::GamePlay).unwrap();
+        drop(info_lock);
+
+        if let Some(err) = err {
+            return self.addr.do_send(BotMsg {
+                channel_id: 1,
+                msg: err,
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        if let Err(err) = self.set_spectator(msg.user_id, true) {
+            return self.addr.do_send(BotMsg {
+                channel_id: 1,
+                msg: err,
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        self.addr.do_send(UpdatePers(msg.user_id));
+        self.addr.do_send(BotMsg {
+            channel_id: gameplay,
+            msg: ttp::user_spectate(msg.user_id),
+            reply_to: None,
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::SpectatorJoin(msg.user_id.to_string()),
+        });
+    }
+}
+
+impl Handler<Unspectate> for Game {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: Unspectate,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let gameplay = *self
+            .info
+            .lock()
+            .unwrap()
+            .channels
+            .get(&GameChannel::GamePlay)
+            .unwrap();
+
+        if let Err(err) = self.set_spectator(msg.user_id, false) {
+            return self.addr.do_send(BotMsg {
+                channel_id: 1,
+                msg: err,
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        self.addr.do_send(UpdatePers(msg.user_id));
+        self.addr.do_send(BotMsg {
+            channel_id: gameplay,
+            msg: ttp::user_unspectate(msg.user_id),
+            reply_to: None,
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::SpectatorLeave(msg.user_id.to_string()),
+        });
+    }
+}
+
+impl Handler<Sub> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Sub, _: &mut Self::Context) -> Self::Result {
+        let mut info_lock = self.info.lock().unwrap();
+        let err = if !info_lock.is_started {
+            Some(ttp::game_is_not_started())
+        } else if info_lock.is_ended || info_lock.is_stopped {
+            Some(ttp::stop_game())
+        } else if info_lock.users.contains(&msg.user_id) {
+            Some(ttp::aready_in_game())
+        } else if info_lock.spectators.contains(&msg.user_id)
+            || info_lock.narrator == Some(msg.user_id)
+        {
+            Some(ttp::sub_not_allowed())
+        } else if !info_lock.users.contains(&msg.target) {
+            Some(ttp::player_not_in_game(msg.target))
+        } else if !info_lock.away.contains_key(&msg.target)
+            && !info_lock.afk.contains(&msg.target)
+        {
+            Some(ttp::sub_target_present(msg.target))
+        } else {
+            None
+        };
+        if let Some(err) = err {
+            drop(info_lock);
+            return self.addr.do_send(BotMsg {
+                channel_id: 1,
+                msg: err,
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        info_lock.sub_requests.insert(msg.user_id, msg.target);
+        let gameplay = *info_lock.channels.get(&GameChannel::GamePlay).unwrap();
+        drop(info_lock);
+        self.save_snapshot();
+
+        self.addr.do_send(BotMsg {
+            channel_id: 1,
+            msg: ttp::sub_requested(),
+            reply_to: Some(msg.msg_id),
+        });
+        self.addr.do_send(BotMsg {
+            channel_id: gameplay,
+            msg: ttp::sub_request(&self.bot_prefix, msg.user_id, msg.target),
+            reply_to: None,
+        });
+    }
+}
+
+impl Handler<Approve> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Approve, _: &mut Self::Context) -> Self::Result {
+        let gameplay = *self
+            .info
+            .lock()
+            .unwrap()
+            .channels
+            .get(&GameChannel::GamePlay)
+            .unwrap();
+        if !self.assert_cmd_in(
+            Some(gameplay),
+            msg.user_id,
+            msg.msg_id,
+            msg.channel_id,
+        ) {
+            return;
+        }
+
+        let res = msg
+            .target
+            .map_err(|_| ttp::must_mention())
+            .and_then(|sub| {
+                let mut info_lock = self.info.lock().unwrap();
+                let old = *info_lock
+                    .sub_requests
+                    .get(&sub)
+                    .ok_or(ttp::no_sub_request(sub))?;
+                // only the host or the player giving up the seat may agree
+                if info_lock.host != Some(msg.user_id) && old != msg.user_id {
+                    return Err(ttp::cannot_approve());
+                }
+                info_lock.sub_requests.remove(&sub);
+                Ok((sub, old))
+            })
+            .and_then(|(sub, old)| {
+                self.substitute(old, sub).map(|_| (sub, old))
+            });
+        let (sub, old) = match res {
+            Ok(res) => res,
+            Err(err) => {
+                return self.addr.do_send(BotMsg {
+                    channel_id: msg.channel_id,
+                    msg: err,
+                    reply_to: Some(msg.msg_id),
+                })
+            }
+        };
+
+        let mut info_lock = self.info.lock().unwrap();
+        let werewolf = *info_lock.channels.get(&GameChannel::WereWolf).unwrap();
+        let player = info_lock.players.get_mut(&sub).unwrap();
+        player.on_start_game(&self.bot_prefix);
+        if roles::is_wolf(player.get_role_name()) {
+            self.addr.do_send(BotMsg {
+                channel_id: werewolf,
+                msg: ttp::new_wolf(sub),
+                reply_to: None,
+            });
+        }
+        drop(info_lock);
+        self.save_snapshot();
+
+        self.addr.do_send(UpdatePers(old));
+        self.addr.do_send(UpdatePers(sub));
+        self.addr.do_send(BotMsg {
+            channel_id: gameplay,
+            msg: ttp::substituted(old, sub),
+            reply_to: None,
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::PlayerSubstituted(
+                old.to_string(),
+                sub.to_string(),
+            ),
+        });
+    }
+}
+
+impl Handler<Start> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Start, _: &mut Self::Context) -> Self::Result {
+        if !self.assert_lobby_cmd(msg.user_id, msg.msg_id, msg.channel_id) {
+            return;
+        }
+
+        if msg.force
+            && !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+        {
+            return;
+        }
+
+        let channel_id = msg.channel_id;
+
+        let num_users = self.info.lock().unwrap().users.len();
+        if num_users < self.info.lock().unwrap().settings.min_players {
+            return self.addr.do_send(BotMsg {
+                channel_id,
+                msg: ttp::not_enough_player(num_users),
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        self.info.lock().unwrap().vote_starts.insert(msg.user_id);
+
+        let numvote = self.info.lock().unwrap().vote_starts.len();
+        let numplayer = self.info.lock().unwrap().users.len();
+        let percent = self.info.lock().unwrap().settings.start_vote;
+        if !msg.force && !is_enough_vote(percent, numvote, numplayer) {
+            self.addr.do_send(GameMsg {
+                game_id: self.id,
+                event: GameEvent::UserStart(msg.user_id.to_string()),
             });
             return self.addr.do_send(BotMsg {
                 channel_id,
@@ -307,119 +1342,638 @@ impl Handler<Start> for Game {
             });
         }
 
-        match self.start() {
+        self.start_game(channel_id, Some(msg.msg_id));
+    }
+}
+
+impl Handler<AutoStart> for Game {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: AutoStart,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let mut info_lock = self.info.lock().unwrap();
+        if info_lock.is_started || info_lock.auto_start != Some(msg.0) {
+            return;
+        }
+        info_lock.auto_start = None;
+        let gameplay = *info_lock.channels.get(&GameChannel::GamePlay).unwrap();
+        drop(info_lock);
+
+        self.start_game(gameplay, None);
+    }
+}
+
+impl Handler<CancelStart> for Game {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: CancelStart,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        if !self.assert_lobby_cmd(msg.user_id, msg.msg_id, msg.channel_id)
+            || !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+        {
+            return;
+        }
+
+        if self.info.lock().unwrap().auto_start.is_none() {
+            return self.addr.do_send(BotMsg {
+                channel_id: msg.channel_id,
+                msg: ttp::no_auto_start(),
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        self.cancel_auto_start();
+    }
+}
+
+impl Handler<Stop> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Stop, _: &mut Self::Context) -> Self::Result {
+        if !self.must_in_game(msg.user_id, msg.msg_id) {
+            return;
+        }
+
+        let gameplay = *self
+            .info
+            .lock()
+            .unwrap()
+            .channels
+            .get(&GameChannel::GamePlay)
+            .unwrap();
+        let channel_id = msg.channel_id;
+
+        if channel_id != 1 && channel_id != gameplay {
+            return self.addr.do_send(BotMsg {
+                channel_id,
+                msg: ttp::must_in_channel(1),
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        if !self.info.lock().unwrap().is_ended {
+            self.info.lock().unwrap().vote_stops.insert(msg.user_id);
+
+            let numvote = self.info.lock().unwrap().vote_stops.len();
+            let numplayer = self.info.lock().unwrap().users.len();
+            let percent = self.info.lock().unwrap().settings.stop_vote;
+            if !is_enough_vote(percent, numvote, numplayer) {
+                self.save_snapshot();
+                self.addr.do_send(GameMsg {
+                    game_id: self.id,
+                    event: GameEvent::UserStop(msg.user_id.to_string()),
+                });
+                return self.addr.do_send(BotMsg {
+                    channel_id,
+                    msg: ttp::user_stop(msg.user_id, numvote, numplayer),
+                    reply_to: Some(msg.msg_id),
+                });
+            }
+        }
+
+        // spectators are dropped by stop, so collect them first
+        let info_lock = self.info.lock().unwrap();
+        let uids = info_lock
+            .users
+            .iter()
+            .chain(info_lock.spectators.iter())
+            .chain(info_lock.narrator.iter())
+            .cloned()
+            .collect::<Vec<i64>>();
+        drop(info_lock);
+
+        if let Err(err) = self.stop() {
+            return self.addr.do_send(BotMsg {
+                channel_id,
+                msg: err,
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        self.addr.do_send(BotMsg {
+            channel_id,
+            msg: ttp::stop_game(),
+            reply_to: Some(msg.msg_id),
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::StopGame_(uids.clone()),
+        });
+
+        for user in uids {
+            self.addr.do_send(UpdatePers(user));
+        }
+
+        let next = self.info.lock().unwrap().next_flag.clone();
+        next.wake();
+    }
+}
+
+impl Handler<Next> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Next, _: &mut Self::Context) -> Self::Result {
+        let gameplay = *self
+            .info
+            .lock()
+            .unwrap()
+            .channels
+            .get(&GameChannel::GamePlay)
+            .unwrap();
+        if !self.assert_cmd_in(
+            Some(gameplay),
+            msg.user_id,
+            msg.msg_id,
+            msg.channel_id,
+        ) {
+            return;
+        }
+
+        self.info.lock().unwrap().vote_nexts.insert(msg.user_id);
+
+        if !self.check_next() {
+            let numvote = self.info.lock().unwrap().get_next_votes();
+            let numplayer = self.info.lock().unwrap().users.len();
+            self.addr.do_send(GameMsg {
+                game_id: self.id,
+                event: GameEvent::UserNext(msg.user_id.to_string()),
+            });
+            self.addr.do_send(BotMsg {
+                channel_id: gameplay,
+                msg: ttp::user_next(msg.user_id, numvote, numplayer),
+                reply_to: Some(msg.msg_id),
+            });
+        }
+    }
+}
+
+impl Handler<Away> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Away, ctx: &mut Self::Context) -> Self::Result {
+        let mut info_lock = self.info.lock().unwrap();
+        if !info_lock.users.contains(&msg.user_id) {
+            return;
+        }
+
+        if !msg.is_away {
+            info_lock.afk.remove(&msg.user_id);
+            if info_lock.away.remove(&msg.user_id).is_some() {
+                drop(info_lock);
+                self.save_snapshot();
+                self.addr.do_send(GameMsg {
+                    game_id: self.id,
+                    event: GameEvent::PlayerBack(msg.user_id.to_string()),
+                });
+            }
+            return;
+        }
+
+        info_lock.away_gen += 1;
+        let gen = info_lock.away_gen;
+        info_lock.away.insert(msg.user_id, gen);
+        let grace = info_lock.settings.away_grace;
+        drop(info_lock);
+        self.save_snapshot();
+
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::PlayerAway(msg.user_id.to_string()),
+        });
+
+        let game = ctx.address();
+        let user_id = msg.user_id;
+        let fut = async move {
+            actix::clock::delay_for(Duration::from_secs(grace)).await;
+            game.do_send(AwayTimeout { user_id, gen });
+        };
+        Arbiter::spawn(fut);
+    }
+}
+
+impl Handler<AwayTimeout> for Game {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: AwayTimeout,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let mut info_lock = self.info.lock().unwrap();
+        if info_lock.away.get(&msg.user_id) != Some(&msg.gen) {
+            return;
+        }
+        info_lock.afk.insert(msg.user_id);
+        drop(info_lock);
+        self.save_snapshot();
+        let info_lock = self.info.lock().unwrap();
+
+        let alive = info_lock
+            .players
+            .get(&msg.user_id)
+            .is_some_and(|player| player.is_alive());
+        if !alive
+            || info_lock.is_ended
+            || info_lock.settings.afk_policy == AfkPolicy::Wait
+        {
+            return;
+        }
+        let gameplay = *info_lock.channels.get(&GameChannel::GamePlay).unwrap();
+        let policy = info_lock.settings.afk_policy;
+        drop(info_lock);
+
+        self.addr.do_send(BotMsg {
+            channel_id: gameplay,
+            msg: ttp::player_afk(msg.user_id, policy),
+            reply_to: None,
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::PlayerAfk(msg.user_id.to_string()),
+        });
+
+        match policy {
+            AfkPolicy::SkipAction => self.done_night_action(msg.user_id),
+            AfkPolicy::AutoNext => {
+                self.check_next();
+            }
+            AfkPolicy::Wait => {}
+        }
+    }
+}
+
+impl Handler<Pause> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Pause, _: &mut Self::Context) -> Self::Result {
+        if !self.assert_running(msg.user_id, msg.msg_id, msg.channel_id) {
+            return;
+        }
+
+        let mut info_lock = self.info.lock().unwrap();
+        if info_lock.timer.is_paused() != msg.resume {
+            drop(info_lock);
+            return self.addr.do_send(BotMsg {
+                channel_id: msg.channel_id,
+                msg: if msg.resume {
+                    ttp::game_not_paused()
+                } else {
+                    ttp::game_is_paused(&self.bot_prefix)
+                },
+                reply_to: Some(msg.msg_id),
+            });
+        }
+
+        // the host decides alone, everybody else votes
+        if info_lock.host != Some(msg.user_id) {
+            info_lock.vote_pauses.insert(msg.user_id);
+            let numvote = info_lock.vote_pauses.len();
+            let numplayer = info_lock.users.len();
+            let percent = info_lock.settings.pause_vote;
+            if !is_enough_vote(percent, numvote, numplayer) {
+                drop(info_lock);
+                self.save_snapshot();
+                return self.addr.do_send(BotMsg {
+                    channel_id: msg.channel_id,
+                    msg: ttp::user_pause(
+                        msg.user_id,
+                        numvote,
+                        numplayer,
+                        msg.resume,
+                    ),
+                    reply_to: Some(msg.msg_id),
+                });
+            }
+        }
+
+        info_lock.vote_pauses.clear();
+        if msg.resume {
+            info_lock.timer.resume();
+        } else {
+            info_lock.timer.pause();
+        }
+        let remaining = info_lock.timer.remaining_secs();
+        drop(info_lock);
+        self.save_deadline();
+        self.save_snapshot();
+
+        self.addr.do_send(BotMsg {
+            channel_id: msg.channel_id,
+            msg: if msg.resume {
+                ttp::game_resumed(remaining)
+            } else {
+                ttp::game_paused(remaining)
+            },
+            reply_to: Some(msg.msg_id),
+        });
+        self.send_timer();
+    }
+}
+
+impl Handler<Extend> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Extend, _: &mut Self::Context) -> Self::Result {
+        if !self.assert_running(msg.user_id, msg.msg_id, msg.channel_id)
+            || !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+        {
+            return;
+        }
+
+        let mut info_lock = self.info.lock().unwrap();
+        info_lock.timer.extend(msg.secs);
+        let remaining = info_lock.timer.remaining_secs();
+        drop(info_lock);
+        self.save_deadline();
+        self.save_snapshot();
+
+        self.addr.do_send(BotMsg {
+            channel_id: msg.channel_id,
+            msg: ttp::time_extended(msg.secs, remaining),
+            reply_to: Some(msg.msg_id),
+        });
+        self.send_timer();
+    }
+}
+
+impl Handler<Narrator> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Narrator, _: &mut Self::Context) -> Self::Result {
+        // the host may take a running game back from its narrator
+        if msg.target.is_none() && self.info.lock().unwrap().is_started {
+            if !self.must_in_game(msg.user_id, msg.msg_id)
+                || !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+            {
+                return;
+            }
+            let narrator = match self.drop_narrator() {
+                Ok(narrator) => narrator,
+                Err(err) => {
+                    return self.addr.do_send(BotMsg {
+                        channel_id: msg.channel_id,
+                        msg: err,
+                        reply_to: Some(msg.msg_id),
+                    })
+                }
+            };
+            self.save_deadline();
+            self.save_snapshot();
+
+            let gameplay = *self
+                .info
+                .lock()
+                .unwrap()
+                .channels
+                .get(&GameChannel::GamePlay)
+                .unwrap();
+            self.addr.do_send(BotMsg {
+                channel_id: gameplay,
+                msg: ttp::narrator_dropped(),
+                reply_to: None,
+            });
+            self.addr.do_send(UpdatePers(narrator));
+            self.addr.do_send(GameMsg {
+                game_id: self.id,
+                event: GameEvent::NewNarrator(None),
+            });
+            self.send_timer();
+            return;
+        }
+
+        if !self.assert_lobby_cmd(msg.user_id, msg.msg_id, msg.channel_id)
+            || !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+        {
+            return;
+        }
+
+        let narrator = match msg.target {
+            None => Ok(None),
+            Some(Err(_)) => Err(ttp::must_mention()),
+            Some(Ok(target))
+                if self.info.lock().unwrap().users.contains(&target) =>
+            {
+                Err(ttp::player_in_game(target))
+            }
+            Some(Ok(target)) => Ok(Some(target)),
+        }
+        .and_then(|narrator| self.set_narrator(narrator).map(|_| narrator));
+        let narrator = match narrator {
+            Ok(narrator) => narrator,
             Err(err) => {
                 return self.addr.do_send(BotMsg {
-                    channel_id,
+                    channel_id: msg.channel_id,
                     msg: err,
                     reply_to: Some(msg.msg_id),
                 })
             }
-            Ok(roles) => self.addr.do_send(BotMsg {
-                channel_id: gameplay,
-                msg: ttp::roles_list(&roles),
-                reply_to: None,
-            }),
-        }
+        };
 
         self.addr.do_send(BotMsg {
-            channel_id: 1,
-            msg: ttp::start_game(),
+            channel_id: msg.channel_id,
+            msg: ttp::new_narrator(narrator),
             reply_to: Some(msg.msg_id),
         });
+        if let Some(narrator) = narrator {
+            self.addr.do_send(UpdatePers(narrator));
+        }
         self.addr.do_send(GameMsg {
             game_id: self.id,
-            event: GameEvent::StartGame,
+            event: GameEvent::NewNarrator(narrator.map(|id| id.to_string())),
         });
+    }
+}
 
-        for &user in self.info.lock().unwrap().users.iter() {
-            self.addr.do_send(UpdatePers(user));
+impl Handler<Advance> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Advance, _: &mut Self::Context) -> Self::Result {
+        if !self.assert_narrator(msg.user_id, msg.msg_id, msg.channel_id) {
+            return;
         }
+
+        let mut info_lock = self.info.lock().unwrap();
+        info_lock.vote_nexts = HashSet::new();
+        let next = info_lock.next_flag.clone();
+        drop(info_lock);
+
+        next.wake();
     }
 }
 
-impl Handler<Stop> for Game {
+impl Handler<Override> for Game {
     type Result = ();
 
-    fn handle(&mut self, msg: Stop, _: &mut Self::Context) -> Self::Result {
-        if !self.must_in_game(msg.user_id, msg.msg_id) {
+    fn handle(&mut self, msg: Override, _: &mut Self::Context) -> Self::Result {
+        if !self.assert_narrator(msg.user_id, msg.msg_id, msg.channel_id) {
             return;
         }
 
-        let gameplay = *self
-            .info
-            .lock()
-            .unwrap()
-            .channels
-            .get(&GameChannel::GamePlay)
-            .unwrap();
+        let mut info_lock = self.info.lock().unwrap();
+        let res = info_lock.get_target(msg.target, Some(true));
+        if let Ok(target) = res {
+            // only one forced lynch makes sense during the day
+            if msg.kill && info_lock.is_day {
+                info_lock.overrides.retain(|_, kill| !*kill);
+            }
+            info_lock.overrides.insert(target, msg.kill);
+        }
+        drop(info_lock);
+        self.save_snapshot();
+
+        self.addr.do_send(BotMsg {
+            channel_id: msg.channel_id,
+            msg: match res {
+                Ok(target) => ttp::override_set(target, msg.kill),
+                Err(err) => err,
+            },
+            reply_to: Some(msg.msg_id),
+        });
+    }
+}
+
+impl Handler<Set> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Set, ctx: &mut Self::Context) -> Self::Result {
+        if !self.assert_lobby_cmd(msg.user_id, msg.msg_id, msg.channel_id)
+            || !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+        {
+            return;
+        }
         let channel_id = msg.channel_id;
 
-        if channel_id != 1 && channel_id != gameplay {
+        if let Err(err) = self.update_settings(|s| s.set(&msg.key, &msg.value))
+        {
             return self.addr.do_send(BotMsg {
                 channel_id,
-                msg: ttp::must_in_channel(1),
+                msg: err,
                 reply_to: Some(msg.msg_id),
             });
         }
 
-        if !self.info.lock().unwrap().is_ended {
-            self.info.lock().unwrap().vote_stops.insert(msg.user_id);
+        self.addr.do_send(BotMsg {
+            channel_id,
+            msg: ttp::set_setting(&msg.key, &msg.value),
+            reply_to: Some(msg.msg_id),
+        });
+        self.check_auto_start(ctx, false);
+    }
+}
 
-            let numvote = self.info.lock().unwrap().vote_stops.len();
-            let numplayer = self.info.lock().unwrap().users.len();
-            if numvote * 3 < numplayer * 2 {
-                self.addr.do_send(GameMsg {
-                    game_id: self.id,
-                    event: GameEvent::UserStop(msg.user_id.to_string()),
+impl Handler<Settings> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Settings, _: &mut Self::Context) -> Self::Result {
+        if !self.must_in_game(msg.user_id, msg.msg_id) {
+            return;
+        }
+
+        self.addr.do_send(BotMsg {
+            channel_id: msg.channel_id,
+            msg: ttp::settings(&self.info.lock().unwrap().settings),
+            reply_to: Some(msg.msg_id),
+        });
+    }
+}
+
+impl Handler<Mode> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Mode, ctx: &mut Self::Context) -> Self::Result {
+        let name = match msg.name {
+            Some(name) => name,
+            None => {
+                if !self.must_in_game(msg.user_id, msg.msg_id) {
+                    return;
+                }
+                let res = ModeConfig::load().map(|config| {
+                    ttp::mode_list(
+                        &config.names(),
+                        &self.info.lock().unwrap().settings.mode,
+                    )
                 });
                 return self.addr.do_send(BotMsg {
-                    channel_id,
-                    msg: ttp::user_stop(msg.user_id, numvote, numplayer),
+                    channel_id: msg.channel_id,
+                    msg: res.unwrap_or_else(|err| err),
                     reply_to: Some(msg.msg_id),
                 });
             }
+        };
+
+        if !self.assert_lobby_cmd(msg.user_id, msg.msg_id, msg.channel_id)
+            || !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+        {
+            return;
         }
 
-        if let Err(err) = self.stop() {
+        let res = self.update_settings(|s| s.set_mode(&name));
+        self.addr.do_send(BotMsg {
+            channel_id: msg.channel_id,
+            msg: match res {
+                Ok(_) => ttp::set_mode(&name),
+                Err(err) => err,
+            },
+            reply_to: Some(msg.msg_id),
+        });
+        self.check_auto_start(ctx, false);
+    }
+}
+
+impl Handler<Roles> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Roles, ctx: &mut Self::Context) -> Self::Result {
+        if let DeckCmd::Show = msg.cmd {
+            if !self.must_in_game(msg.user_id, msg.msg_id) {
+                return;
+            }
             return self.addr.do_send(BotMsg {
-                channel_id,
-                msg: err,
+                channel_id: msg.channel_id,
+                msg: ttp::deck(&self.info.lock().unwrap().settings.deck),
                 reply_to: Some(msg.msg_id),
             });
         }
 
+        if !self.assert_lobby_cmd(msg.user_id, msg.msg_id, msg.channel_id)
+            || !self.assert_host(msg.user_id, msg.msg_id, msg.channel_id)
+        {
+            return;
+        }
+
+        let res = self.update_settings(|s| match &msg.cmd {
+            DeckCmd::Add(role) => s.add_role(role).map(|_| ()),
+            DeckCmd::Remove(role) => s.remove_role(role).map(|_| ()),
+            DeckCmd::Preset(name) => s.set_preset(name),
+            DeckCmd::Clear | DeckCmd::Show => {
+                s.deck.clear();
+                s.mode = None;
+                Ok(())
+            }
+        });
+
         self.addr.do_send(BotMsg {
-            channel_id,
-            msg: ttp::stop_game(),
+            channel_id: msg.channel_id,
+            msg: match res {
+                Ok(_) => ttp::deck(&self.info.lock().unwrap().settings.deck),
+                Err(err) => err,
+            },
             reply_to: Some(msg.msg_id),
         });
-        self.addr.do_send(GameMsg {
-            game_id: self.id,
-            event: GameEvent::StopGame_(
-                self.info
-                    .lock()
-                    .unwrap()
-                    .users
-                    .iter()
-                    .map(|&uid| uid)
-                    .collect(),
-            ),
-        });
-
-        for &user in self.info.lock().unwrap().users.iter() {
-            self.addr.do_send(UpdatePers(user));
-        }
-
-        let next = self.info.lock().unwrap().next_flag.clone();
-        next.wake();
+        self.check_auto_start(ctx, false);
     }
 }
 
-impl Handler<Next> for Game {
+impl Handler<Vote> for Game {
     type Result = ();
 
-    fn handle(&mut self, msg: Next, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: Vote, _: &mut Self::Context) -> Self::Result {
         let gameplay = *self
             .info
             .lock()
@@ -436,33 +1990,46 @@ impl Handler<Next> for Game {
             return;
         }
 
-        self.info.lock().unwrap().vote_nexts.insert(msg.user_id);
-
-        let numvote = self.info.lock().unwrap().vote_nexts.len();
-        let numplayer = self.info.lock().unwrap().users.len();
-        if numvote * 3 < numplayer * 2 {
-            self.addr.do_send(GameMsg {
-                game_id: self.id,
-                event: GameEvent::UserNext(msg.user_id.to_string()),
-            });
+        let vote_user = self
+            .info
+            .lock()
+            .unwrap()
+            .get_target(msg.vote_for, Some(true));
+        if let Err(err) = vote_user {
             return self.addr.do_send(BotMsg {
                 channel_id: gameplay,
-                msg: ttp::user_next(msg.user_id, numvote, numplayer),
+                msg: err,
                 reply_to: Some(msg.msg_id),
             });
         }
+        let vote_user = vote_user.unwrap();
 
-        self.info.lock().unwrap().vote_nexts = HashSet::new();
-
-        let next = self.info.lock().unwrap().next_flag.clone();
-        next.wake();
+        self.info
+            .lock()
+            .unwrap()
+            .vote_kill
+            .insert(msg.user_id, vote_user);
+        self.save_snapshot();
+        self.addr.do_send(BotMsg {
+            channel_id: gameplay,
+            msg: ttp::vote_kill(msg.user_id, vote_user),
+            reply_to: Some(msg.msg_id),
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::UserVote {
+                user_id: msg.user_id.to_string(),
+                vote_for: vote_user.to_string(),
+            },
+        });
+        self.send_vote_tally();
     }
 }
 
-impl Handler<Vote> for Game {
+impl Handler<Unvote> for Game {
     type Result = ();
 
-    fn handle(&mut self, msg: Vote, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: Unvote, _: &mut Self::Context) -> Self::Result {
         let gameplay = *self
             .info
             .lock()
@@ -479,33 +2046,54 @@ impl Handler<Vote> for Game {
             return;
         }
 
-        let user_list = self.info.lock().unwrap().get_alives();
-        let vote_user = get_from_target(&user_list, msg.vote_for, Some(true));
-        if let Err(err) = vote_user {
+        let old = self.info.lock().unwrap().vote_kill.remove(&msg.user_id);
+        if old.is_none() {
             return self.addr.do_send(BotMsg {
                 channel_id: gameplay,
-                msg: err,
+                msg: ttp::not_voted(),
                 reply_to: Some(msg.msg_id),
             });
         }
-        let vote_user = vote_user.unwrap();
+        self.save_snapshot();
 
-        self.info
-            .lock()
-            .unwrap()
-            .vote_kill
-            .insert(msg.user_id, vote_user);
         self.addr.do_send(BotMsg {
             channel_id: gameplay,
-            msg: ttp::vote_kill(msg.user_id, vote_user),
+            msg: ttp::unvote(msg.user_id),
             reply_to: Some(msg.msg_id),
         });
         self.addr.do_send(GameMsg {
             game_id: self.id,
-            event: GameEvent::UserVote {
-                user_id: msg.user_id.to_string(),
-                vote_for: vote_user.to_string(),
-            },
+            event: GameEvent::UserUnvote(msg.user_id.to_string()),
+        });
+        self.send_vote_tally();
+    }
+}
+
+impl Handler<Votes> for Game {
+    type Result = ();
+
+    fn handle(&mut self, msg: Votes, _: &mut Self::Context) -> Self::Result {
+        let gameplay = *self
+            .info
+            .lock()
+            .unwrap()
+            .channels
+            .get(&GameChannel::GamePlay)
+            .unwrap();
+        if !self.assert_cmd_in(
+            Some(gameplay),
+            msg.user_id,
+            msg.msg_id,
+            msg.channel_id,
+        ) {
+            return;
+        }
+
+        let tally = self.info.lock().unwrap().get_vote_tally();
+        self.addr.do_send(BotMsg {
+            channel_id: gameplay,
+            msg: ttp::vote_tally(&tally),
+            reply_to: Some(msg.msg_id),
         });
     }
 }
@@ -533,8 +2121,8 @@ impl Handler<Kill> for Game {
             return;
         }
 
-        let user_list = self.info.lock().unwrap().get_alives();
-        let target = get_from_target(&user_list, msg.target, Some(true));
+        let target =
+            self.info.lock().unwrap().get_target(msg.target, Some(true));
         if let Err(err) = target {
             return self.addr.do_send(BotMsg {
                 channel_id: msg.channel_id,
@@ -558,6 +2146,8 @@ impl Handler<Kill> for Game {
             msg: ttp::wolf_kill(msg.user_id, target),
             reply_to: Some(msg.msg_id),
         });
+        self.night_intent(msg.user_id, "kill", &[target]);
+        self.done_night_action(msg.user_id);
     }
 }
 
@@ -577,8 +2167,8 @@ impl Handler<Guard> for Game {
             return;
         }
 
-        let user_list = self.info.lock().unwrap().get_alives();
-        let target = get_from_target(&user_list, msg.target, Some(true));
+        let target =
+            self.info.lock().unwrap().get_target(msg.target, Some(true));
         if let Err(err) = target {
             return self.addr.do_send(BotMsg {
                 channel_id: msg.channel_id,
@@ -611,6 +2201,9 @@ impl Handler<Guard> for Game {
             msg: ttp::guard_success(target),
             reply_to: Some(msg.msg_id),
         });
+        drop(info_lock);
+        self.night_intent(msg.user_id, "guard", &[target]);
+        self.done_night_action(msg.user_id);
     }
 }
 
@@ -630,8 +2223,8 @@ impl Handler<Seer> for Game {
             return;
         }
 
-        let user_list = self.info.lock().unwrap().get_alives();
-        let target = get_from_target(&user_list, msg.target, Some(true));
+        let target =
+            self.info.lock().unwrap().get_target(msg.target, Some(true));
         if let Err(err) = target {
             return self.addr.do_send(BotMsg {
                 channel_id: msg.channel_id,
@@ -650,7 +2243,16 @@ impl Handler<Seer> for Game {
         let is_wolf = player.get_role_name() == roles::WEREWOLF
             || player.get_role_name() == roles::LYCAN;
         if player.get_role_name() == roles::FOX {
-            info_lock.night_pending_kill.insert(target);
+            info_lock
+                .night_pending_kill
+                .insert(target, DeathCause::Vision);
+        }
+        if info_lock.bots.contains(&msg.user_id) {
+            info_lock
+                .bot_notes
+                .entry(msg.user_id)
+                .or_default()
+                .insert(target, is_wolf);
         }
 
         self.addr.do_send(BotMsg {
@@ -658,6 +2260,9 @@ impl Handler<Seer> for Game {
             msg: ttp::seer_use_skill(target, is_wolf),
             reply_to: Some(msg.msg_id),
         });
+        drop(info_lock);
+        self.night_intent(msg.user_id, "seer", &[target]);
+        self.done_night_action(msg.user_id);
     }
 }
 
@@ -677,8 +2282,11 @@ impl Handler<Ship> for Game {
             return;
         }
 
-        let user_list = self.info.lock().unwrap().get_alives();
-        let target1 = get_from_target(&user_list, msg.target1, Some(true));
+        let target1 = self
+            .info
+            .lock()
+            .unwrap()
+            .get_target(msg.target1, Some(true));
         if let Err(err) = target1 {
             return self.addr.do_send(BotMsg {
                 channel_id: msg.channel_id,
@@ -688,7 +2296,11 @@ impl Handler<Ship> for Game {
         }
         let target1 = target1.unwrap();
 
-        let target2 = get_from_target(&user_list, msg.target2, Some(true));
+        let target2 = self
+            .info
+            .lock()
+            .unwrap()
+            .get_target(msg.target2, Some(true));
         if let Err(err) = target2 {
             return self.addr.do_send(BotMsg {
                 channel_id: msg.channel_id,
@@ -729,6 +2341,9 @@ impl Handler<Ship> for Game {
             msg: ttp::shipped_with(target1, p1_role),
             reply_to: None,
         });
+        drop(info_lock);
+        self.night_intent(msg.user_id, "ship", &[target1, target2]);
+        self.done_night_action(msg.user_id);
     }
 }
 
@@ -752,8 +2367,11 @@ impl Handler<Reborn> for Game {
             return;
         }
 
-        let user_list = self.info.lock().unwrap().get_alives();
-        let target = get_from_target(&user_list, msg.target, Some(false));
+        let target = self
+            .info
+            .lock()
+            .unwrap()
+            .get_target(msg.target, Some(false));
         if let Err(err) = target {
             return self.addr.do_send(BotMsg {
                 channel_id: msg.channel_id,
@@ -763,6 +2381,7 @@ impl Handler<Reborn> for Game {
         }
         let target = target.unwrap();
         let mut info_lock = self.info.lock().unwrap();
+        let witch_both = info_lock.settings.witch_both;
         let player = info_lock.players.get_mut(&msg.user_id).unwrap();
 
         if !player.get_power() {
@@ -774,14 +2393,16 @@ impl Handler<Reborn> for Game {
         }
         player.on_use_power();
 
-        if !player.get_mana() {
-            return self.addr.do_send(BotMsg {
-                channel_id: msg.channel_id,
-                msg: ttp::out_of_mana(),
-                reply_to: Some(msg.msg_id),
-            });
+        if !witch_both {
+            if !player.get_mana() {
+                return self.addr.do_send(BotMsg {
+                    channel_id: msg.channel_id,
+                    msg: ttp::out_of_mana(),
+                    reply_to: Some(msg.msg_id),
+                });
+            }
+            player.on_use_mana();
         }
-        player.on_use_mana();
 
         info_lock.witch_reborn = Some(target);
 
@@ -790,6 +2411,9 @@ impl Handler<Reborn> for Game {
             msg: ttp::reborn_success(target),
             reply_to: Some(msg.msg_id),
         });
+        drop(info_lock);
+        self.night_intent(msg.user_id, "reborn", &[target]);
+        self.done_night_action(msg.user_id);
     }
 }
 
@@ -809,8 +2433,8 @@ impl Handler<Curse> for Game {
             return;
         }
 
-        let user_list = self.info.lock().unwrap().get_alives();
-        let target = get_from_target(&user_list, msg.target, Some(true));
+        let target =
+            self.info.lock().unwrap().get_target(msg.target, Some(true));
         if let Err(err) = target {
             return self.addr.do_send(BotMsg {
                 channel_id: msg.channel_id,
@@ -820,6 +2444,7 @@ impl Handler<Curse> for Game {
         }
         let target = target.unwrap();
         let mut info_lock = self.info.lock().unwrap();
+        let witch_both = info_lock.settings.witch_both;
         let player = info_lock.players.get_mut(&msg.user_id).unwrap();
 
         if !player.get_power2() {
@@ -831,62 +2456,33 @@ impl Handler<Curse> for Game {
         }
         player.on_use_power2();
 
-        if !player.get_mana() {
-            return self.addr.do_send(BotMsg {
-                channel_id: msg.channel_id,
-                msg: ttp::out_of_mana(),
-                reply_to: Some(msg.msg_id),
-            });
+        if !witch_both {
+            if !player.get_mana() {
+                return self.addr.do_send(BotMsg {
+                    channel_id: msg.channel_id,
+                    msg: ttp::out_of_mana(),
+                    reply_to: Some(msg.msg_id),
+                });
+            }
+            player.on_use_mana();
         }
-        player.on_use_mana();
 
-        info_lock.night_pending_kill.insert(target);
+        info_lock
+            .night_pending_kill
+            .insert(target, DeathCause::Curse);
+        info_lock.witch_curse = Some((msg.user_id, target));
 
         self.addr.do_send(BotMsg {
             channel_id: msg.channel_id,
             msg: ttp::curse_success(target),
             reply_to: Some(msg.msg_id),
         });
+        drop(info_lock);
+        self.night_intent(msg.user_id, "curse", &[target]);
+        self.done_night_action(msg.user_id);
     }
 }
 
-// must Some(true) if alive Some(false) if died
-fn get_from_target(
-    (alive, died): &(Vec<i64>, Vec<i64>),
-    target: Result<i64, u16>,
-    must: Option<bool>,
-) -> Result<i64, String> {
-    let target = match target {
-        Ok(id) => Ok(id),
-        Err(idx) => {
-            let idx = idx as usize;
-            let list = match must {
-                Some(false) => &died,
-                _ => &alive,
-            };
-            if idx < 1 || idx > list.len() {
-                Err(ttp::invalid_index(1, list.len()))
-            } else {
-                Ok(list[idx - 1])
-            }
-        }
-    }?;
-
-    if !alive.contains(&target) && !died.contains(&target) {
-        return Err(ttp::player_not_in_game(target));
-    }
-
-    if Some(true) == must && died.contains(&target) {
-        return Err(ttp::player_died());
-    }
-
-    if Some(false) == must && alive.contains(&target) {
-        return Err(ttp::player_still_alive(target));
-    }
-
-    Ok(target)
-}
-
 fn assert_cmd(
     game: &Game,
     roles: &[&'static str],
@@ -0,0 +1,84 @@
+// builders shared by the unit tests of the game modules
+
+use std::collections::HashMap;
+
+use actix::{dev::channel, Addr};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::models::user::RolePreference;
+use crate::ws::ChatServer;
+
+use super::characters::{self, roles, RoleConfig, FRR};
+use super::game::{GameChannel, GameInfo};
+use super::settings::{GameSettings, MAX_PLAYERS, MIN_PLAYERS};
+
+// a six seat table with one card of each kind the tests look at
+pub const DEAL: [(i64, &str); 6] = [
+    (1, roles::WEREWOLF),
+    (2, roles::SEER),
+    (3, roles::VILLAGER),
+    (4, roles::VILLAGER),
+    (5, roles::GUARD),
+    (6, roles::WITCH),
+];
+
+pub fn rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+// user ids 1..=n
+pub fn seats(n: i64) -> Vec<i64> {
+    (1..=n).collect()
+}
+
+// no chat server runs, the address only has to exist
+pub fn addr() -> Addr<ChatServer> {
+    let (tx, _rx) = channel::channel::<ChatServer>(16);
+    Addr::new(tx)
+}
+
+// a game that has not started yet, with users 1..=n in it
+pub fn lobby(n: usize) -> GameInfo {
+    let channels = vec![(GameChannel::GamePlay, 10)].into_iter().collect();
+    let users = seats(n as i64).into_iter().collect();
+    GameInfo::new(channels, users, GameSettings::default())
+}
+
+// gives every user of the deal their card
+pub fn seat(
+    info: &mut GameInfo,
+    deal: &[(i64, &str)],
+    addr: &Addr<ChatServer>,
+) {
+    for &(uid, role) in deal {
+        let player = characters::new_role(role, uid, addr.clone()).unwrap();
+        info.players.insert(uid, player);
+        info.seats.push(uid);
+    }
+}
+
+pub fn table(rules: &[(&str, FRR)]) -> HashMap<String, FRR> {
+    rules
+        .iter()
+        .map(|(role, frr)| (role.to_string(), frr.clone()))
+        .collect()
+}
+
+// every size gets the same rules
+pub fn config(rules: &[(&str, FRR)]) -> RoleConfig {
+    (MIN_PLAYERS..=MAX_PLAYERS)
+        .map(|size| (size, table(rules)))
+        .collect()
+}
+
+pub fn pref(
+    user_id: i64,
+    preferred: &[&str],
+    avoided: &[&str],
+) -> RolePreference {
+    RolePreference {
+        user_id,
+        preferred: preferred.iter().map(|r| r.to_string()).collect(),
+        avoided: avoided.iter().map(|r| r.to_string()).collect(),
+    }
+}
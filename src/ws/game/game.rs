@@ -3,6 +3,9 @@ use diesel::{
     r2d2::{ConnectionManager, PooledConnection},
     PgConnection,
 };
+use rand::prelude::SliceRandom;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use snowflake::SnowflakeIdGenerator;
 
 use std::{
@@ -10,67 +13,141 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use crate::ws::ChatServer;
+use crate::models::game::Game as GameModel;
+use crate::ws::{
+    cmd_parser::{DeathCause, GameEvent},
+    ChatServer,
+};
 use crate::{config::DbPool, db};
 
 use super::characters::{self, player::Player, roles};
+use super::cmds::GameMsg;
 use super::game_loop::GameLoop;
 use super::next::NextFut;
+use super::pseudonym::{self, Masks, Pseudonym};
+use super::settings::{AfkPolicy, GameSettings, RevealRole};
+use super::snapshot::Snapshot;
 use super::text_templates as ttp;
+use super::timer::PhaseTimer;
 
 pub struct GameInfo {
     pub channels: HashMap<GameChannel, i64>,
     pub users: HashSet<i64>,
+    pub host: Option<i64>,
+    pub is_private: bool,
+    pub auto_start: Option<u64>,
+    pub auto_start_gen: u64,
+    pub spectators: HashSet<i64>,
+    // disconnected players with the generation of their grace timer
+    pub away: HashMap<i64, u64>,
+    pub away_gen: u64,
+    pub afk: HashSet<i64>,
+    // players who spoke or acted in the current phase
+    pub active: HashSet<i64>,
+    pub idle_phases: HashMap<i64, u16>,
+    // lobby users asking to take over a seat, with the player they replace
+    pub sub_requests: HashMap<i64, i64>,
+    // non-player participant who runs the phases by hand
+    pub narrator: Option<i64>,
+    // narrator decisions for the end of the phase, true kills and false spares
+    pub overrides: HashMap<i64, bool>,
+    // identities shown in place of the players of an anonymous game
+    pub pseudonyms: HashMap<i64, Pseudonym>,
+    // server side players and what the seer bots found out so far
+    pub bots: HashSet<i64>,
+    pub bot_notes: HashMap<i64, HashMap<i64, bool>>,
     pub players: HashMap<i64, Box<dyn Player>>,
+    pub seats: Vec<i64>,
     pub is_started: bool,
     pub is_ended: bool,
     pub is_stopped: bool,
     pub is_day: bool,
     pub num_day: u16,
+    // the current phase was announced, a resumed loop must not redo it
+    pub phase_started: bool,
 
     pub vote_kill: HashMap<i64, i64>,
     pub wolf_kill: HashMap<i64, i64>,
     pub cupid_couple: HashMap<i64, i64>,
-    pub night_pending_kill: HashSet<i64>,
+    pub night_pending_kill: HashMap<i64, DeathCause>,
+    pub night_pending_actions: HashSet<i64>,
     pub vote_starts: HashSet<i64>,
     pub vote_stops: HashSet<i64>,
     pub vote_nexts: HashSet<i64>,
 
     pub guard_yesterday_target: Option<(i64, u16)>,
     pub witch_reborn: Option<i64>,
+    pub witch_curse: Option<(i64, i64)>,
+
+    pub history: Vec<GameEvent>,
 
     pub next_flag: NextFut,
-    pub timmer: (u64, u64, u64),
+    pub timer: PhaseTimer,
+    // unix deadline of the running phase as last saved in the database
+    pub phase_deadline: Option<i64>,
+    pub vote_pauses: HashSet<i64>,
+    pub settings: GameSettings,
+    // every random draw of the game comes from `rng`, seeded with `seed`
+    pub seed: u64,
+    pub rng: StdRng,
 }
 
 impl GameInfo {
     pub fn new(
         channels: HashMap<GameChannel, i64>,
         users: HashSet<i64>,
+        settings: GameSettings,
     ) -> Self {
         Self {
             channels,
             users,
+            host: None,
+            is_private: false,
+            auto_start: None,
+            auto_start_gen: 0,
+            spectators: HashSet::new(),
+            away: HashMap::new(),
+            away_gen: 0,
+            afk: HashSet::new(),
+            active: HashSet::new(),
+            idle_phases: HashMap::new(),
+            sub_requests: HashMap::new(),
+            narrator: None,
+            overrides: HashMap::new(),
+            pseudonyms: HashMap::new(),
+            bots: HashSet::new(),
+            bot_notes: HashMap::new(),
             players: HashMap::new(),
+            seats: vec![],
             is_started: false,
             is_ended: false,
             is_stopped: false,
             is_day: true,
             num_day: 0,
+            phase_started: false,
 
             vote_kill: HashMap::new(),
             wolf_kill: HashMap::new(),
             cupid_couple: HashMap::new(),
-            night_pending_kill: HashSet::new(),
+            night_pending_kill: HashMap::new(),
+            night_pending_actions: HashSet::new(),
             vote_starts: HashSet::new(),
             vote_stops: HashSet::new(),
             vote_nexts: HashSet::new(),
 
             guard_yesterday_target: None,
             witch_reborn: None,
+            witch_curse: None,
+
+            history: vec![],
 
             next_flag: NextFut::new(),
-            timmer: (180, 60, 30),
+            timer: PhaseTimer::new(0),
+            phase_deadline: None,
+            vote_pauses: HashSet::new(),
+            settings,
+            seed: 0,
+            rng: StdRng::seed_from_u64(0),
         }
     }
 
@@ -86,15 +163,185 @@ impl GameInfo {
             }
         }
 
-        alive.sort();
-        died.sort();
+        alive.sort_by_key(|uid| self.get_seat(*uid));
+        died.sort_by_key(|uid| self.get_seat(*uid));
         (alive, died)
     }
+
+    pub fn get_away(&self) -> HashSet<i64> {
+        self.away.keys().cloned().collect()
+    }
+
+    // afk players the game acts for under the given policy
+    pub fn get_afk(&self, policy: AfkPolicy) -> HashSet<i64> {
+        if self.settings.afk_policy == policy {
+            self.afk.clone()
+        } else {
+            HashSet::new()
+        }
+    }
+
+    // real to pseudonym ids while the game hides identities, reversed
+    // to read what the players wrote
+    pub fn get_masks(&self, reverse: bool) -> Masks {
+        if !self.is_started || self.is_ended {
+            return Masks::new();
+        }
+        self.pseudonyms
+            .iter()
+            .map(|(&uid, p)| if reverse { (p.id, uid) } else { (uid, p.id) })
+            .collect()
+    }
+
+    pub fn get_next_votes(&self) -> usize {
+        self.vote_nexts
+            .union(&self.get_afk(AfkPolicy::AutoNext))
+            .count()
+    }
+
+    // moves every reference to `old` over to `new`
+    pub fn rename_user(&mut self, old: i64, new: i64) {
+        fn rename_set(set: &mut HashSet<i64>, old: i64, new: i64) {
+            if set.remove(&old) {
+                set.insert(new);
+            }
+        }
+        fn rename_key<V>(map: &mut HashMap<i64, V>, old: i64, new: i64) {
+            if let Some(v) = map.remove(&old) {
+                map.insert(new, v);
+            }
+        }
+        fn rename_pairs(map: &mut HashMap<i64, i64>, old: i64, new: i64) {
+            rename_key(map, old, new);
+            for v in map.values_mut().filter(|v| **v == old) {
+                *v = new;
+            }
+        }
+        let rename = |id: &mut i64| {
+            if *id == old {
+                *id = new;
+            }
+        };
+
+        rename_set(&mut self.users, old, new);
+        rename_set(&mut self.night_pending_actions, old, new);
+        rename_set(&mut self.vote_starts, old, new);
+        rename_set(&mut self.vote_stops, old, new);
+        rename_set(&mut self.vote_nexts, old, new);
+        rename_set(&mut self.spectators, old, new);
+        rename_key(&mut self.players, old, new);
+        rename_key(&mut self.pseudonyms, old, new);
+        // a human taking over a bot seat plays on their own
+        self.bots.remove(&old);
+        self.bot_notes.remove(&old);
+        rename_key(&mut self.night_pending_kill, old, new);
+        rename_pairs(&mut self.vote_kill, old, new);
+        rename_pairs(&mut self.wolf_kill, old, new);
+        rename_pairs(&mut self.cupid_couple, old, new);
+        for v in self.sub_requests.values_mut().filter(|v| **v == old) {
+            *v = new;
+        }
+        self.seats.iter_mut().for_each(rename);
+        self.host.iter_mut().for_each(rename);
+        self.witch_reborn.iter_mut().for_each(rename);
+        if let Some((target, _)) = self.guard_yesterday_target.as_mut() {
+            rename(target);
+        }
+        if let Some((witch, target)) = self.witch_curse.as_mut() {
+            rename(witch);
+            rename(target);
+        }
+
+        // the newcomer starts with a clean slate
+        self.away.remove(&old);
+        self.afk.remove(&old);
+        self.active.remove(&old);
+        self.idle_phases.remove(&old);
+    }
+
+    pub fn get_state(&self) -> &'static str {
+        if self.is_ended || self.is_stopped {
+            "ended"
+        } else if self.is_started {
+            "started"
+        } else {
+            "waiting"
+        }
+    }
+
+    pub fn get_seat(&self, user_id: i64) -> Option<usize> {
+        self.seats
+            .iter()
+            .position(|&uid| uid == user_id)
+            .map(|idx| idx + 1)
+    }
+
+    // must Some(true) if alive Some(false) if died
+    pub fn get_target(
+        &self,
+        target: Result<i64, u16>,
+        must: Option<bool>,
+    ) -> Result<i64, String> {
+        let (alive, died) = self.get_alives();
+        let target = match target {
+            Ok(id) => Ok(id),
+            Err(seat) => {
+                let seat = seat as usize;
+                if seat < 1 || seat > self.seats.len() {
+                    Err(ttp::invalid_index(1, self.seats.len()))
+                } else {
+                    Ok(self.seats[seat - 1])
+                }
+            }
+        }?;
+
+        if !alive.contains(&target) && !died.contains(&target) {
+            return Err(ttp::player_not_in_game(target));
+        }
+
+        if Some(true) == must && died.contains(&target) {
+            return Err(ttp::player_died());
+        }
+
+        if Some(false) == must && alive.contains(&target) {
+            return Err(ttp::player_still_alive(target));
+        }
+
+        Ok(target)
+    }
+
+    pub fn reveal_role_on_death(
+        &self,
+        user_id: i64,
+        cause: DeathCause,
+    ) -> Option<&'static str> {
+        let reveal = match self.settings.reveal_role {
+            RevealRole::Never => false,
+            RevealRole::Always => true,
+            RevealRole::Lynched => cause == DeathCause::Lynched,
+        };
+        if !reveal {
+            return None;
+        }
+        self.players.get(&user_id).map(|p| p.get_role_name())
+    }
+
+    pub fn get_vote_tally(&self) -> Vec<(i64, u16)> {
+        let mut h = HashMap::new();
+        for (_, &uid) in self.vote_kill.iter() {
+            *h.entry(uid).or_insert(0) += 1;
+        }
+
+        let mut tally = h.into_iter().collect::<Vec<(i64, u16)>>();
+        tally.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        tally
+    }
 }
 
 #[derive(Clone)]
 pub struct Game {
     pub id: i64,
+    pub name: String,
     pub addr: Addr<ChatServer>,
     pub db_pool: DbPool,
     pub id_gen: Arc<Mutex<SnowflakeIdGenerator>>,
@@ -103,12 +350,20 @@ pub struct Game {
     pub info: Arc<Mutex<GameInfo>>,
 }
 
-#[derive(PartialEq, Eq, Hash, Debug)]
+// what a new room is created with
+pub struct GameOptions {
+    pub id: i64,
+    pub name: String,
+    pub is_private: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum GameChannel {
     GamePlay,
     WereWolf,
     Cemetery,
     Personal(i64),
+    Narrator,
 }
 
 pub fn get_conn(
@@ -124,21 +379,30 @@ pub fn get_conn(
 
 impl Game {
     pub fn new(
-        id: i64,
+        options: GameOptions,
         addr: Addr<ChatServer>,
         db_pool: DbPool,
         id_gen: Arc<Mutex<SnowflakeIdGenerator>>,
         bot_id: i64,
         bot_prefix: String,
     ) -> Self {
+        let GameOptions {
+            id,
+            name,
+            is_private,
+        } = options;
         let conn = get_conn(db_pool.clone());
-        db::game::create(&conn, id).unwrap();
+        let settings = GameSettings::default();
+        db::game::create(&conn, id, &name, &settings.to_json(), is_private)
+            .unwrap();
 
-        let info =
-            Arc::new(Mutex::new(GameInfo::new(HashMap::new(), HashSet::new())));
+        let mut info = GameInfo::new(HashMap::new(), HashSet::new(), settings);
+        info.is_private = is_private;
+        let info = Arc::new(Mutex::new(info));
 
         let mut s = Self {
             id,
+            name,
             addr,
             db_pool,
             id_gen,
@@ -157,7 +421,33 @@ impl Game {
         s
     }
 
+    pub fn load_all_from_db(
+        addr: Addr<ChatServer>,
+        db_pool: DbPool,
+        id_gen: Arc<Mutex<SnowflakeIdGenerator>>,
+        bot_id: i64,
+        bot_prefix: String,
+    ) -> Vec<Self> {
+        let conn = get_conn(db_pool.clone());
+        let games = db::game::get_all(&conn).unwrap_or(vec![]);
+
+        games
+            .into_iter()
+            .filter_map(|game| {
+                Self::load_from_db(
+                    game,
+                    addr.clone(),
+                    db_pool.clone(),
+                    id_gen.clone(),
+                    bot_id,
+                    bot_prefix.clone(),
+                )
+            })
+            .collect()
+    }
+
     pub fn load_from_db(
+        game: GameModel,
         addr: Addr<ChatServer>,
         db_pool: DbPool,
         id_gen: Arc<Mutex<SnowflakeIdGenerator>>,
@@ -165,25 +455,50 @@ impl Game {
         bot_prefix: String,
     ) -> Option<Self> {
         let conn = get_conn(db_pool.clone());
-        let id = db::game::get(&conn)?.id;
+        let id = game.id;
+        let name = game.name;
         let channels = db::game::get_channels(&conn, id).ok()?;
         let users = db::game::get_users(&conn, id).ok()?;
 
+        let bots = users.iter().filter(|u| u.is_bot).map(|u| u.id).collect();
         let users = users.iter().map(|u| u.id).collect();
+        let num_channels = channels.len();
         let channels = channels
             .iter()
-            .map(|cl| match cl.channel_name.as_str() {
+            .filter_map(|cl| match cl.channel_name.as_str() {
                 "gameplay" => Some((GameChannel::GamePlay, cl.id)),
                 "werewolf" => Some((GameChannel::WereWolf, cl.id)),
                 "cemetery" => Some((GameChannel::Cemetery, cl.id)),
-                _ => return None,
+                // personal and narrator channels come back with the snapshot
+                _ => None,
             })
-            .collect::<Option<HashMap<GameChannel, i64>>>()?;
+            .collect::<HashMap<GameChannel, i64>>();
+        // a started game from before snapshots can't be rebuilt
+        if channels.len() < num_channels && game.snapshot.is_none() {
+            return None;
+        }
+
+        let settings = GameSettings::from_json(&game.settings);
+        let mut info = GameInfo::new(channels, users, settings);
+        info.host = game.host_id;
+        info.is_private = game.is_private;
+        info.phase_deadline = game.phase_deadline;
+        info.bots = bots;
 
-        let info = Arc::new(Mutex::new(GameInfo::new(channels, users)));
+        // a running game carries on from its last snapshot, an ended one is
+        // not worth bringing back
+        if let Some(snapshot) = game.snapshot {
+            let snapshot = serde_json::from_str::<Snapshot>(&snapshot).ok()?;
+            if snapshot.is_ended {
+                return None;
+            }
+            snapshot.restore(&mut info, addr.clone()).ok()?;
+        }
+        let info = Arc::new(Mutex::new(info));
 
         Some(Self {
             id,
+            name,
             addr,
             db_pool,
             id_gen,
@@ -213,10 +528,40 @@ impl Game {
         db::channel::set_pers(&conn, new_id2, user_id, gameplay, true, true)
             .unwrap();
 
+        if info.host.is_none() {
+            db::game::set_host(&conn, self.id, Some(user_id))
+                .map_err(|err| err.to_string())?;
+            info.host = Some(user_id);
+        }
+
         info.users.insert(user_id);
         Ok(())
     }
 
+    // a bot account that sits in no game, a new one when all are busy, is
+    // picked and seated in one step so two lobbies can't take the same bot
+    pub fn add_free_bot(&mut self) -> Result<i64, String> {
+        let conn = get_conn(self.db_pool.clone());
+        let bots = db::user::get_bots(&conn).map_err(|err| err.to_string())?;
+        let bot_id = match bots
+            .iter()
+            .find(|bot| db::game::get_from_user(&conn, bot.id).is_err())
+        {
+            Some(bot) => bot.id,
+            None => {
+                let id = self.id_gen.lock().unwrap().real_time_generate();
+                let name = format!("Bot {}", bots.len() + 1);
+                db::user::create_bot(&conn, id, &name)
+                    .map_err(|err| err.to_string())?
+                    .id
+            }
+        };
+
+        self.add_user(bot_id)?;
+        self.info.lock().unwrap().bots.insert(bot_id);
+        Ok(bot_id)
+    }
+
     pub fn remove_user(&mut self, user_id: i64) -> Result<(), String> {
         let mut info = self.info.lock().unwrap();
 
@@ -240,6 +585,164 @@ impl Game {
         info.users.remove(&user_id);
         info.vote_starts.remove(&user_id);
         info.vote_stops.remove(&user_id);
+        info.away.remove(&user_id);
+        info.afk.remove(&user_id);
+        info.bots.remove(&user_id);
+
+        // bots never run the room
+        if info.host == Some(user_id) {
+            let host = info
+                .users
+                .iter()
+                .filter(|uid| !info.bots.contains(uid))
+                .min()
+                .cloned();
+            db::game::set_host(&conn, self.id, host)
+                .map_err(|err| err.to_string())?;
+            info.host = host;
+        }
+
+        Ok(())
+    }
+
+    // hands the seat of `old` to `new`, role and channels included
+    pub fn substitute(&mut self, old: i64, new: i64) -> Result<(), String> {
+        let mut info = self.info.lock().unwrap();
+        let conn = get_conn(self.db_pool.clone());
+
+        if db::game::get_from_user(&conn, new).is_ok() {
+            return Err(ttp::in_other_game());
+        }
+        if !info.players.contains_key(&old) {
+            return Err(ttp::player_not_in_game(old));
+        }
+
+        db::game::replace_user(&conn, self.id, old, new)
+            .map_err(|err| err.to_string())?;
+
+        let mut id_lock = self.id_gen.lock().unwrap();
+        for (channel, &channel_id) in info.channels.iter() {
+            if let GameChannel::Personal(uid) = channel {
+                if *uid != old {
+                    continue;
+                }
+            }
+            let (readable, sendable) =
+                db::channel::get_pers(&conn, old, channel_id)
+                    .map(|p| (p.readable, p.sendable))
+                    .unwrap_or((false, false));
+            db::channel::set_pers(
+                &conn,
+                id_lock.real_time_generate(),
+                new,
+                channel_id,
+                readable,
+                sendable,
+            )
+            .map_err(|err| err.to_string())?;
+            db::channel::set_pers(
+                &conn,
+                id_lock.real_time_generate(),
+                old,
+                channel_id,
+                false,
+                false,
+            )
+            .map_err(|err| err.to_string())?;
+        }
+
+        if let Some(personal) =
+            info.channels.remove(&GameChannel::Personal(old))
+        {
+            info.channels.insert(GameChannel::Personal(new), personal);
+        }
+        // a spectator taking the seat stops being one
+        info.spectators.remove(&new);
+        info.rename_user(old, new);
+        *info.players.get_mut(&new).unwrap().get_playerid() = new;
+        Ok(())
+    }
+
+    pub fn set_narrator(
+        &mut self,
+        narrator: Option<i64>,
+    ) -> Result<(), String> {
+        let mut info = self.info.lock().unwrap();
+        let gameplay = *info.channels.get(&GameChannel::GamePlay).unwrap();
+        let conn = get_conn(self.db_pool.clone());
+        let mut id_lock = self.id_gen.lock().unwrap();
+
+        if let Some(old) = info.narrator {
+            db::channel::set_pers(
+                &conn,
+                id_lock.real_time_generate(),
+                old,
+                gameplay,
+                false,
+                false,
+            )
+            .map_err(|err| err.to_string())?;
+        }
+        if let Some(new) = narrator {
+            db::channel::set_pers(
+                &conn,
+                id_lock.real_time_generate(),
+                new,
+                gameplay,
+                true,
+                true,
+            )
+            .map_err(|err| err.to_string())?;
+        }
+
+        info.narrator = narrator;
+        Ok(())
+    }
+
+    // hands a running game back to the timer, with a fresh phase time
+    pub fn drop_narrator(&mut self) -> Result<i64, String> {
+        let mut info = self.info.lock().unwrap();
+        let narrator = info.narrator.ok_or(ttp::no_narrator())?;
+        let conn = get_conn(self.db_pool.clone());
+        let mut id_lock = self.id_gen.lock().unwrap();
+
+        for (_, &channel_id) in info.channels.iter() {
+            db::channel::set_pers(
+                &conn,
+                id_lock.real_time_generate(),
+                narrator,
+                channel_id,
+                false,
+                false,
+            )
+            .map_err(|err| err.to_string())?;
+        }
+
+        info.narrator = None;
+        info.overrides.clear();
+        let timecount = info.settings.phase_time(info.is_day);
+        info.timer = PhaseTimer::new(timecount);
+        Ok(narrator)
+    }
+
+    pub fn set_host(&mut self, host: i64) -> Result<(), String> {
+        let conn = get_conn(self.db_pool.clone());
+        db::game::set_host(&conn, self.id, Some(host))
+            .map_err(|err| err.to_string())?;
+        self.info.lock().unwrap().host = Some(host);
+        Ok(())
+    }
+
+    pub fn update_settings<F>(&mut self, f: F) -> Result<(), String>
+    where
+        F: FnOnce(&mut GameSettings) -> Result<(), String>,
+    {
+        let mut info = self.info.lock().unwrap();
+        f(&mut info.settings)?;
+
+        let conn = get_conn(self.db_pool.clone());
+        db::game::update_settings(&conn, self.id, &info.settings.to_json())
+            .map_err(|err| err.to_string())?;
 
         Ok(())
     }
@@ -290,12 +793,26 @@ impl Game {
     pub fn start(&mut self) -> Result<HashMap<String, usize>, String> {
         let mut info = self.info.lock().unwrap();
 
+        info.settings.check_deck(info.users.len())?;
+        let seed = match info.settings.seed {
+            0 => rand::thread_rng().gen(),
+            seed => seed,
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let conn = get_conn(self.db_pool.clone());
+        let prefs = db::user::get_all_role_prefs(
+            &conn,
+            &info.users.iter().cloned().collect::<Vec<i64>>(),
+        )
+        .map_err(|err| err.to_string())?;
         let mut players = characters::rand_roles(
             &info.users.iter().collect::<Vec<&i64>>(),
+            &info.settings,
+            &prefs,
+            &mut rng,
             self.addr.clone(),
         )?;
 
-        let conn = get_conn(self.db_pool.clone());
         let mut id_lock = self.id_gen.lock().unwrap();
         let mut roles = HashMap::new();
 
@@ -360,15 +877,112 @@ impl Game {
             }
         }
 
+        let mut seats = players.keys().cloned().collect::<Vec<i64>>();
+        seats.sort();
+        seats.shuffle(&mut rng);
+
         info.players = players;
+        info.seats = seats;
+        info.active.clear();
+
+        if info.settings.anonymous {
+            let mut uids = info.users.iter().cloned().collect::<Vec<i64>>();
+            uids.sort();
+            let ids = uids
+                .iter()
+                .map(|_| id_lock.real_time_generate())
+                .collect::<Vec<i64>>();
+            let pseudonyms = pseudonym::gen_pseudonyms(&ids, &mut rng);
+            info.pseudonyms = uids.into_iter().zip(pseudonyms).collect();
+            self.addr.do_send(GameMsg {
+                game_id: self.id,
+                event: GameEvent::Pseudonyms(
+                    info.pseudonyms
+                        .iter()
+                        .map(|(uid, p)| {
+                            (
+                                uid.to_string(),
+                                (p.name.clone(), p.avatar.clone()),
+                            )
+                        })
+                        .collect(),
+                ),
+            });
+        }
+
+        if let Some(narrator) = info.narrator {
+            let new_id1 = id_lock.real_time_generate();
+            let channel_id = id_lock.real_time_generate();
+            db::game::add_channel(
+                &conn,
+                new_id1,
+                self.id,
+                channel_id,
+                "narrator".to_string(),
+            )
+            .map_err(|err| err.to_string())?;
+            info.channels.insert(GameChannel::Narrator, channel_id);
+
+            // the narrator reads everything but only talks in gameplay and
+            // in the narrator channel
+            for (channel, &channel_id) in info.channels.iter() {
+                let sendable = *channel == GameChannel::GamePlay
+                    || *channel == GameChannel::Narrator;
+                db::channel::set_pers(
+                    &conn,
+                    id_lock.real_time_generate(),
+                    narrator,
+                    channel_id,
+                    true,
+                    sendable,
+                )
+                .map_err(|err| err.to_string())?;
+            }
+        }
+
+        db::game::set_seed(&conn, self.id, Some(seed as i64))
+            .map_err(|err| err.to_string())?;
+        info.seed = seed;
+        info.rng = rng;
+
+        let mode = info.settings.mode.clone();
+        info.history.push(GameEvent::GameMode(mode));
 
         let game_loop = GameLoop::new(self.clone());
         actix::Arbiter::spawn(game_loop);
 
         info.is_started = true;
+        let snapshot = serde_json::to_string(&Snapshot::take(&mut info)).ok();
+        db::game::set_snapshot(&conn, self.id, snapshot).ok();
         Ok(roles)
     }
 
+    // stores everything needed to resume the game after a restart
+    pub fn save_snapshot(&self) {
+        let mut info = self.info.lock().unwrap();
+        if !info.is_started || info.is_stopped {
+            return;
+        }
+        let snapshot = serde_json::to_string(&Snapshot::take(&mut info)).ok();
+        drop(info);
+
+        let conn = get_conn(self.db_pool.clone());
+        db::game::set_snapshot(&conn, self.id, snapshot).ok();
+    }
+
+    pub fn save_deadline(&self) {
+        let info = self.info.lock().unwrap();
+        let deadline = if info.timer.is_paused() {
+            None
+        } else {
+            Some(info.timer.deadline_unix())
+        };
+        drop(info);
+
+        let conn = get_conn(self.db_pool.clone());
+        db::game::set_phase_deadline(&conn, self.id, deadline).ok();
+    }
+
     pub fn stop(&mut self) -> Result<(), String> {
         let mut info = self.info.lock().unwrap();
 
@@ -377,10 +991,46 @@ impl Game {
         }
         let conn = get_conn(self.db_pool.clone());
         db::game::delete(&conn, self.id).map_err(|err| err.to_string())?;
+        info.spectators.clear();
         info.is_stopped = true;
         Ok(())
     }
 
+    // spectators only ever read gameplay and, if allowed, the cemetery
+    pub fn set_spectator(
+        &mut self,
+        user_id: i64,
+        is_spectator: bool,
+    ) -> Result<(), String> {
+        let mut info = self.info.lock().unwrap();
+        let mut channels = vec![GameChannel::GamePlay];
+        if info.settings.spectate_cemetery {
+            channels.push(GameChannel::Cemetery);
+        }
+
+        let conn = get_conn(self.db_pool.clone());
+        let mut id_lock = self.id_gen.lock().unwrap();
+        for channel in channels {
+            let channel_id = *info.channels.get(&channel).unwrap();
+            db::channel::set_pers(
+                &conn,
+                id_lock.real_time_generate(),
+                user_id,
+                channel_id,
+                is_spectator,
+                false,
+            )
+            .map_err(|err| err.to_string())?;
+        }
+
+        if is_spectator {
+            info.spectators.insert(user_id);
+        } else {
+            info.spectators.remove(&user_id);
+        }
+        Ok(())
+    }
+
     pub fn assert_cmd_in(
         &self,
         channel_id: Option<i64>,
@@ -435,6 +1085,15 @@ impl Game {
             return false;
         }
 
+        if self.info.lock().unwrap().timer.is_paused() {
+            self.addr.do_send(BotMsg {
+                channel_id: msg_channel_id,
+                msg: ttp::game_is_paused(&self.bot_prefix),
+                reply_to: Some(msg_id),
+            });
+            return false;
+        }
+
         true
     }
 
@@ -2,15 +2,19 @@ use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
 use actix::Arbiter;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use crate::db;
-use crate::ws::cmd_parser::GameEvent;
+use crate::ws::cmd_parser::{DeathCause, GameEvent, NightResult};
 use crate::ws::game::cmds::GameMsg;
 
-use super::characters::player::PlayerStatus;
+use super::bot;
+use super::characters::player::{Player, PlayerStatus};
 use super::characters::roles;
-use super::cmds::{BotMsg, UpdatePers};
-use super::game::GameChannel;
+use super::cmds::{BotMsg, BotPlay, UpdatePers};
+use super::game::{GameChannel, GameInfo};
+use super::settings::AfkPolicy;
+use super::timer::PhaseTimer;
 use super::{text_templates as ttp, Game};
 
 use super::game::get_conn;
@@ -25,6 +29,8 @@ struct CurrentState {
     num_day: u16,
     alive: Vec<i64>,
     died: Vec<i64>,
+    seats: Vec<i64>,
+    away: HashSet<i64>,
     gameplay: i64,
     werewolf: i64,
     cemetery: i64,
@@ -41,10 +47,16 @@ impl std::ops::Deref for GameLoop {
 impl GameLoop {
     pub async fn new(game: Game) {
         let game = Self { game };
-        game.run().await;
+        game.run(false).await;
     }
 
-    pub async fn run(&self) {
+    // picks a game loaded from its snapshot back up at the saved phase
+    pub async fn resume(game: Game) {
+        let game = Self { game };
+        game.run(true).await;
+    }
+
+    pub async fn run(&self, resume: bool) {
         let next = self.info.lock().unwrap().next_flag.clone();
 
         let gameplay = *self
@@ -71,46 +83,13 @@ impl GameLoop {
 
         let bot_prefix = self.bot_prefix.clone();
 
-        let mut info_lock = self.info.lock().unwrap();
-        let (alive, _died) = info_lock.get_alives();
-        let wolf_list = alive
-            .iter()
-            .filter(|uid| {
-                let role = info_lock.players.get(uid).unwrap().get_role_name();
-                role == roles::WEREWOLF || role == roles::SUPERWOLF
-            })
-            .collect();
-        for (&uid, player) in info_lock.players.iter_mut() {
-            player.on_start_game(&bot_prefix);
-            let role = player.get_role_name();
-            if role == roles::WEREWOLF || role == roles::SUPERWOLF {
-                self.addr.do_send(BotMsg {
-                    channel_id: werewolf,
-                    msg: ttp::new_wolf(uid),
-                    reply_to: None,
-                });
-            } else if role == roles::CUPID {
-                self.addr.do_send(BotMsg {
-                    channel_id: *player.get_channelid(),
-                    msg: ttp::cupid_action(&bot_prefix),
-                    reply_to: None,
-                });
-                self.addr.do_send(BotMsg {
-                    channel_id: *player.get_channelid(),
-                    msg: ttp::player_list(&alive, true),
-                    reply_to: None,
-                });
-            } else if role == roles::BETRAYER {
-                self.addr.do_send(BotMsg {
-                    channel_id: *player.get_channelid(),
-                    msg: ttp::wolf_list(&wolf_list),
-                    reply_to: None,
-                });
-            }
+        let seats = self.info.lock().unwrap().seats.clone();
+        if !resume {
+            self.do_intro(&bot_prefix, werewolf);
         }
-        drop(info_lock);
 
         let winner;
+        let mut resuming = resume;
         loop {
             let is_day = self.info.lock().unwrap().is_day;
             let num_day = self.info.lock().unwrap().num_day;
@@ -121,6 +100,8 @@ impl GameLoop {
                 num_day,
                 alive,
                 died,
+                seats: seats.clone(),
+                away: self.info.lock().unwrap().get_away(),
                 gameplay,
                 werewolf,
                 cemetery,
@@ -128,27 +109,59 @@ impl GameLoop {
 
             println!("start {} {}", num_day, is_day);
 
-            self.addr.do_send(BotMsg {
-                channel_id: gameplay,
-                msg: ttp::new_phase(&bot_prefix, num_day, is_day),
-                reply_to: None,
-            });
-            self.addr.do_send(GameMsg {
-                game_id: self.id,
-                event: GameEvent::NewPhase { num_day, is_day },
-            });
+            // a resumed phase was already announced and started
+            let phase_started =
+                resuming && self.info.lock().unwrap().phase_started;
+            resuming = false;
 
-            if is_day {
-                self.do_start_day(&state);
-            } else {
-                self.do_start_night(&state);
-            }
+            if !phase_started {
+                // one stream per phase, so a resumed game draws the same
+                let mut info_lock = self.info.lock().unwrap();
+                let phase = (num_day as u64) << 1 | is_day as u64;
+                info_lock.rng = StdRng::seed_from_u64(info_lock.seed ^ phase);
+                drop(info_lock);
 
-            for (_, player) in self.info.lock().unwrap().players.iter_mut() {
-                player.on_phase(num_day, is_day);
-            }
+                self.addr.do_send(BotMsg {
+                    channel_id: gameplay,
+                    msg: ttp::new_phase(&bot_prefix, num_day, is_day),
+                    reply_to: None,
+                });
+                self.addr.do_send(GameMsg {
+                    game_id: self.id,
+                    event: GameEvent::NewPhase { num_day, is_day },
+                });
 
-            self.start_timmer();
+                if is_day {
+                    self.do_start_day(&state);
+                } else {
+                    self.do_start_night(&state);
+                }
+
+                for (_, player) in self.info.lock().unwrap().players.iter_mut()
+                {
+                    player.on_phase(num_day, is_day);
+                }
+                self.info.lock().unwrap().phase_started = true;
+                self.save_snapshot();
+            }
+            self.do_bot_actions(&bot_prefix);
+
+            // the timer is held while a narrator runs the phase and takes
+            // over if the narrator is dropped
+            let narrator = {
+                let info_lock = self.info.lock().unwrap();
+                info_lock.narrator.and(
+                    info_lock.channels.get(&GameChannel::Narrator).cloned(),
+                )
+            };
+            if let Some(narrator) = narrator {
+                self.addr.do_send(BotMsg {
+                    channel_id: narrator,
+                    msg: ttp::narrator_phase(&bot_prefix),
+                    reply_to: None,
+                });
+            }
+            self.start_timmer(phase_started);
             next.wait().await;
 
             if is_day {
@@ -156,24 +169,36 @@ impl GameLoop {
             } else {
                 self.do_end_night(&state);
             }
+            self.do_check_idle(&state);
 
             println!("stop");
 
+            let mut info_lock = self.info.lock().unwrap();
             if !is_day {
-                self.info.lock().unwrap().num_day += 1;
+                info_lock.num_day += 1;
             }
-            self.info.lock().unwrap().is_day = !is_day;
+            info_lock.is_day = !is_day;
+            info_lock.phase_started = false;
+            drop(info_lock);
 
-            if let Some(role) = self.get_wining_role() {
-                winner = role;
+            let wining_role = self.get_wining_role();
+            if wining_role.is_some() {
                 self.info.lock().unwrap().is_ended = true;
+            }
+            self.save_snapshot();
+            if let Some(role) = wining_role {
+                winner = role;
                 break;
             }
         }
 
+        let mode = self.info.lock().unwrap().settings.mode.clone();
+        let ranked = self.info.lock().unwrap().settings.ranked;
         for (&uid, player) in self.info.lock().unwrap().players.iter_mut() {
             player.on_end_game();
-            self.update_win(uid, winner.1.contains(&uid));
+            if ranked {
+                self.update_win(uid, winner.1.contains(&uid), &mode);
+            }
             self.set_pers(uid, gameplay, true, true);
         }
 
@@ -192,6 +217,36 @@ impl GameLoop {
             game_id: self.id,
             event: GameEvent::EndGame { winner },
         });
+        // the seed gives the whole deal away, so it waits for the end
+        let seed = GameEvent::Seed(self.info.lock().unwrap().seed.to_string());
+        self.info.lock().unwrap().history.push(seed.clone());
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: seed,
+        });
+        let pseudonyms = self.info.lock().unwrap().pseudonyms.clone();
+        if !pseudonyms.is_empty() {
+            self.addr.do_send(BotMsg {
+                channel_id: gameplay,
+                msg: ttp::reveal_identities(&pseudonyms),
+                reply_to: None,
+            });
+            self.addr.do_send(GameMsg {
+                game_id: self.id,
+                event: GameEvent::Identities(
+                    pseudonyms
+                        .iter()
+                        .map(|(uid, p)| (p.id.to_string(), uid.to_string()))
+                        .collect(),
+                ),
+            });
+        }
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::GameHistory(
+                self.info.lock().unwrap().history.clone(),
+            ),
+        });
 
         println!("game wait to stop");
         self.wait_stop();
@@ -203,7 +258,12 @@ impl GameLoop {
         println!("alive: {:?}", state.alive);
         self.addr.do_send(BotMsg {
             channel_id: state.gameplay,
-            msg: ttp::player_list(&state.alive, true),
+            msg: ttp::player_list(
+                &state.alive,
+                &state.seats,
+                &state.away,
+                true,
+            ),
             reply_to: None,
         });
 
@@ -217,11 +277,42 @@ impl GameLoop {
             self.set_pers(user_id, state.gameplay, true, false);
         }
 
-        let top_vote = get_top_vote(&mut self.info.lock().unwrap().vote_kill);
+        let votes = self.info.lock().unwrap().vote_kill.clone();
+        self.addr.do_send(BotMsg {
+            channel_id: state.gameplay,
+            msg: ttp::vote_record(state.num_day, &votes),
+            reply_to: None,
+        });
+        self.info
+            .lock()
+            .unwrap()
+            .history
+            .push(GameEvent::VoteRecord(
+                state.num_day,
+                votes
+                    .iter()
+                    .map(|(uid, vote_for)| {
+                        (uid.to_string(), vote_for.to_string())
+                    })
+                    .collect(),
+            ));
+
+        let mut top_vote =
+            get_top_vote(&mut self.info.lock().unwrap().vote_kill);
+        let overrides =
+            std::mem::take(&mut self.info.lock().unwrap().overrides);
+        if let Some((&uid, _)) = overrides.iter().find(|(_, &kill)| kill) {
+            top_vote = Some((uid, 0));
+        }
+        if let Some((uid, _)) = top_vote {
+            if overrides.get(&uid) == Some(&false) {
+                top_vote = None;
+            }
+        }
 
-        let mut cupid_couple = None;
+        let mut info_lock = self.info.lock().unwrap();
+        let mut deaths = vec![];
         if let Some((uid, _)) = top_vote {
-            let mut info_lock = self.info.lock().unwrap();
             let player = info_lock.players.get_mut(&uid).unwrap();
             if player.get_killed(false) {
                 if player.get_role_name() == roles::WEREWOLF
@@ -236,13 +327,9 @@ impl GameLoop {
                     msg: ttp::after_death(uid),
                     reply_to: None,
                 });
-                self.addr.do_send(GameMsg {
-                    game_id: self.id,
-                    event: GameEvent::PlayerDied(uid.to_string()),
-                });
+                deaths.push((uid, DeathCause::Lynched));
 
                 if let Some(&couple) = info_lock.cupid_couple.get(&uid) {
-                    cupid_couple = Some((uid, couple));
                     let player = info_lock.players.get_mut(&couple).unwrap();
                     player.get_killed(true);
                     if player.get_role_name() == roles::WEREWOLF
@@ -257,27 +344,21 @@ impl GameLoop {
                         msg: ttp::after_death(couple),
                         reply_to: None,
                     });
-                    self.addr.do_send(GameMsg {
-                        game_id: self.id,
-                        event: GameEvent::PlayerDied(couple.to_string()),
-                    });
+                    deaths.push((couple, DeathCause::Grief));
                 }
             }
         }
 
-        self.addr.do_send(BotMsg {
-            channel_id: state.gameplay,
-            msg: ttp::execution(top_vote),
-            reply_to: None,
-        });
-
-        if let Some((died, follow)) = cupid_couple {
+        if deaths.is_empty() {
             self.addr.do_send(BotMsg {
                 channel_id: state.gameplay,
-                msg: ttp::couple_died(died, follow, false),
+                msg: ttp::no_execution(),
                 reply_to: None,
             });
         }
+        for (uid, cause) in deaths {
+            self.announce_death(&info_lock, state, uid, cause);
+        }
     }
 
     fn do_start_night(&self, state: &CurrentState) {
@@ -288,7 +369,12 @@ impl GameLoop {
         });
         self.addr.do_send(BotMsg {
             channel_id: state.werewolf,
-            msg: ttp::player_list(&state.alive, true),
+            msg: ttp::player_list(
+                &state.alive,
+                &state.seats,
+                &state.away,
+                true,
+            ),
             reply_to: None,
         });
 
@@ -299,54 +385,148 @@ impl GameLoop {
             }
         }
 
-        for (_uid, player) in info_lock.players.iter_mut() {
+        let skip_afk = info_lock.get_afk(AfkPolicy::SkipAction);
+        let mut pending_actions = HashSet::new();
+        for (&uid, player) in info_lock.players.iter_mut() {
             player.on_action(&self.bot_prefix);
+            if player.is_alive()
+                && !skip_afk.contains(&uid)
+                && owe_night_action(player.as_mut())
+            {
+                pending_actions.insert(uid);
+            }
             if [roles::GUARD, roles::SEER, roles::WITCH]
                 .contains(&player.get_role_name())
             {
                 let &mut personal_channel = player.get_channelid();
                 self.addr.do_send(BotMsg {
                     channel_id: personal_channel,
-                    msg: ttp::player_list(&state.alive, true),
+                    msg: ttp::player_list(
+                        &state.alive,
+                        &state.seats,
+                        &state.away,
+                        true,
+                    ),
                     reply_to: None,
                 });
                 if roles::WITCH == player.get_role_name() {
                     self.addr.do_send(BotMsg {
                         channel_id: personal_channel,
-                        msg: ttp::player_list(&state.died, false),
+                        msg: ttp::player_list(
+                            &state.died,
+                            &state.seats,
+                            &state.away,
+                            false,
+                        ),
                         reply_to: None,
                     });
                 }
             }
         }
+        info_lock.night_pending_actions = pending_actions;
     }
 
     fn do_end_night(&self, state: &CurrentState) {
         let mut info_lock = self.info.lock().unwrap();
-        if let Some((uid, _)) = get_top_vote(&mut info_lock.wolf_kill) {
-            info_lock.night_pending_kill.insert(uid);
+        let wolves = info_lock.wolf_kill.keys().cloned().collect::<Vec<i64>>();
+        let wolf_target = get_top_vote(&mut info_lock.wolf_kill);
+        // a bite outranks a curse on the same target
+        if let Some((uid, _)) = wolf_target {
+            info_lock.night_pending_kill.insert(uid, DeathCause::Wolves);
         }
+        // the narrator's word beats the guard
+        for (uid, kill) in std::mem::take(&mut info_lock.overrides) {
+            if !kill {
+                info_lock.night_pending_kill.remove(&uid);
+                continue;
+            }
+            info_lock
+                .night_pending_kill
+                .entry(uid)
+                .or_insert(DeathCause::Wolves);
+            let status = info_lock.players.get_mut(&uid).unwrap().get_status();
+            if *status == PlayerStatus::Protected {
+                *status = PlayerStatus::Alive;
+            }
+        }
+        let attacked = info_lock.night_pending_kill.clone();
 
         let mut killed = vec![];
+        let mut deaths = vec![];
         let mut cupid_couple = None;
-        for user_id in info_lock.night_pending_kill.clone() {
+        for (user_id, cause) in info_lock.night_pending_kill.clone() {
             let player = info_lock.players.get_mut(&user_id).unwrap();
             if player.get_killed(false) {
                 killed.push(user_id);
+                deaths.push((user_id, cause));
                 if let Some(&couple) = info_lock.cupid_couple.get(&user_id) {
                     let player = info_lock.players.get_mut(&couple).unwrap();
                     player.get_killed(true);
-                    cupid_couple = Some((user_id, couple));
+                    cupid_couple = Some(couple);
+                }
+            }
+        }
+        info_lock.night_pending_kill = HashMap::new();
+        killed.sort_by_key(|&uid| info_lock.get_seat(uid));
+        deaths.sort_by_key(|&(uid, _)| info_lock.get_seat(uid));
+
+        let mut results = vec![];
+        if let Some((target, day)) = info_lock.guard_yesterday_target {
+            if day == state.num_day {
+                let result = if attacked.contains_key(&target)
+                    && !killed.contains(&target)
+                {
+                    NightResult::GuardSaved(target.to_string())
+                } else {
+                    NightResult::GuardQuiet(target.to_string())
+                };
+                for (&uid, player) in info_lock.players.iter() {
+                    if player.get_role_name() == roles::GUARD {
+                        results.push((uid, result.clone()));
+                    }
                 }
             }
         }
-        info_lock.night_pending_kill = HashSet::new();
+        if let Some((target, _)) = wolf_target {
+            let result = if killed.contains(&target) {
+                NightResult::WolfKilled(target.to_string())
+            } else {
+                NightResult::WolfBlocked(target.to_string())
+            };
+            for &uid in wolves.iter() {
+                results.push((uid, result.clone()));
+            }
+        }
+        if let Some((witch, target)) = info_lock.witch_curse.take() {
+            let result = if killed.contains(&target) {
+                NightResult::CurseLanded(target.to_string())
+            } else {
+                NightResult::CurseBlocked(target.to_string())
+            };
+            results.push((witch, result));
+        }
+        for (uid, result) in results {
+            if let Some(&personal) =
+                info_lock.channels.get(&GameChannel::Personal(uid))
+            {
+                self.addr.do_send(BotMsg {
+                    channel_id: personal,
+                    msg: ttp::night_result(&result),
+                    reply_to: None,
+                });
+            }
+            info_lock
+                .history
+                .push(GameEvent::NightResult(uid.to_string(), result));
+        }
 
-        self.addr.do_send(BotMsg {
-            channel_id: state.gameplay,
-            msg: ttp::list_killed(&killed),
-            reply_to: None,
-        });
+        if killed.is_empty() {
+            self.addr.do_send(BotMsg {
+                channel_id: state.gameplay,
+                msg: ttp::quiet_night(),
+                reply_to: None,
+            });
+        }
 
         println!("killed: {:?}", killed);
         for uid in killed {
@@ -364,13 +544,9 @@ impl GameLoop {
                 msg: ttp::after_death(uid),
                 reply_to: None,
             });
-            self.addr.do_send(GameMsg {
-                game_id: self.id,
-                event: GameEvent::PlayerDied(uid.to_string()),
-            });
         }
 
-        if let Some((died, follow)) = cupid_couple {
+        if let Some(follow) = cupid_couple {
             let player = info_lock.players.get_mut(&follow).unwrap();
             let is_wolf = player.get_role_name() == roles::WEREWOLF
                 || player.get_role_name() == roles::SUPERWOLF;
@@ -380,20 +556,16 @@ impl GameLoop {
             if is_wolf {
                 self.set_pers(follow, state.werewolf, false, false);
             }
-            self.addr.do_send(BotMsg {
-                channel_id: state.gameplay,
-                msg: ttp::couple_died(died, follow, false),
-                reply_to: None,
-            });
             self.addr.do_send(BotMsg {
                 channel_id: state.cemetery,
                 msg: ttp::after_death(follow),
                 reply_to: None,
             });
-            self.addr.do_send(GameMsg {
-                game_id: self.id,
-                event: GameEvent::PlayerDied(follow.to_string()),
-            });
+            deaths.push((follow, DeathCause::Grief));
+        }
+
+        for (uid, cause) in deaths {
+            self.announce_death(&info_lock, state, uid, cause);
         }
 
         if let Some(uid) = info_lock.witch_reborn {
@@ -421,13 +593,213 @@ impl GameLoop {
         }
     }
 
-    fn start_timmer(&self) {
+    fn do_check_idle(&self, state: &CurrentState) {
+        let mut info_lock = self.info.lock().unwrap();
+        let active = std::mem::take(&mut info_lock.active);
+        let (warn, kill) =
+            (info_lock.settings.idle_warn, info_lock.settings.idle_kill);
+
+        let mut idlers = vec![];
+        for &uid in state.alive.iter() {
+            if info_lock.bots.contains(&uid) {
+                continue;
+            }
+            // nothing to do at night doesn't make a player idle
+            if !state.is_day {
+                let player = info_lock.players.get_mut(&uid).unwrap();
+                if !owe_night_action(player.as_mut()) {
+                    continue;
+                }
+            }
+            let idle = info_lock.idle_phases.entry(uid).or_default();
+            if active.contains(&uid) {
+                *idle = 0;
+                continue;
+            }
+            *idle += 1;
+
+            if kill > 0 && *idle >= kill {
+                idlers.push(uid);
+            } else if warn > 0 && *idle == warn {
+                let idle = *idle;
+                if let Some(&personal) =
+                    info_lock.channels.get(&GameChannel::Personal(uid))
+                {
+                    self.addr.do_send(BotMsg {
+                        channel_id: personal,
+                        msg: ttp::idle_warning(idle, kill),
+                        reply_to: None,
+                    });
+                }
+            }
+        }
+
+        let mut deaths = vec![];
+        for uid in idlers {
+            if !self.kill_off(&mut info_lock, state, uid) {
+                continue;
+            }
+            deaths.push((uid, DeathCause::Forest));
+
+            if let Some(&couple) = info_lock.cupid_couple.get(&uid) {
+                if self.kill_off(&mut info_lock, state, couple) {
+                    deaths.push((couple, DeathCause::Grief));
+                }
+            }
+        }
+
+        let died = !deaths.is_empty();
+        for (uid, cause) in deaths {
+            self.announce_death(&info_lock, state, uid, cause);
+        }
+        drop(info_lock);
+        if died {
+            self.save_snapshot();
+        }
+    }
+
+    // kills a player no matter the protection, false if already dead
+    fn kill_off(
+        &self,
+        info: &mut GameInfo,
+        state: &CurrentState,
+        user_id: i64,
+    ) -> bool {
+        let player = info.players.get_mut(&user_id).unwrap();
+        if !player.is_alive() {
+            return false;
+        }
+        player.get_killed(true);
+
+        if roles::is_wolf(player.get_role_name()) {
+            self.set_pers(user_id, state.werewolf, false, false);
+        }
+        self.set_pers(user_id, state.gameplay, true, false);
+        self.set_pers(user_id, state.cemetery, true, true);
+        self.addr.do_send(BotMsg {
+            channel_id: state.cemetery,
+            msg: ttp::after_death(user_id),
+            reply_to: None,
+        });
+        true
+    }
+
+    fn announce_death(
+        &self,
+        info: &GameInfo,
+        state: &CurrentState,
+        user_id: i64,
+        cause: DeathCause,
+    ) {
+        let role = info.reveal_role_on_death(user_id, cause);
+        self.addr.do_send(BotMsg {
+            channel_id: state.gameplay,
+            msg: ttp::death_cause(user_id, cause, role),
+            reply_to: None,
+        });
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::PlayerDied(
+                user_id.to_string(),
+                cause,
+                role.map(|r| r.to_string()),
+            ),
+        });
+    }
+
+    fn do_intro(&self, bot_prefix: &str, werewolf: i64) {
+        let mut info_lock = self.info.lock().unwrap();
+        let (alive, _died) = info_lock.get_alives();
+        let seats = info_lock.seats.clone();
+        let away = info_lock.get_away();
+        self.addr.do_send(GameMsg {
+            game_id: self.id,
+            event: GameEvent::Seats(
+                seats.iter().map(|uid| uid.to_string()).collect(),
+            ),
+        });
+        let wolf_list = alive
+            .iter()
+            .filter(|uid| {
+                let role = info_lock.players.get(uid).unwrap().get_role_name();
+                role == roles::WEREWOLF || role == roles::SUPERWOLF
+            })
+            .collect();
+        for (&uid, player) in info_lock.players.iter_mut() {
+            player.on_start_game(bot_prefix);
+            let role = player.get_role_name();
+            if role == roles::WEREWOLF || role == roles::SUPERWOLF {
+                self.addr.do_send(BotMsg {
+                    channel_id: werewolf,
+                    msg: ttp::new_wolf(uid),
+                    reply_to: None,
+                });
+            } else if role == roles::CUPID {
+                self.addr.do_send(BotMsg {
+                    channel_id: *player.get_channelid(),
+                    msg: ttp::cupid_action(bot_prefix),
+                    reply_to: None,
+                });
+                self.addr.do_send(BotMsg {
+                    channel_id: *player.get_channelid(),
+                    msg: ttp::player_list(&alive, &seats, &away, true),
+                    reply_to: None,
+                });
+            } else if role == roles::BETRAYER {
+                self.addr.do_send(BotMsg {
+                    channel_id: *player.get_channelid(),
+                    msg: ttp::wolf_list(&wolf_list),
+                    reply_to: None,
+                });
+            }
+        }
+    }
+
+    // bots type their command after a short think, like a player would
+    fn do_bot_actions(&self, bot_prefix: &str) {
+        let mut info_lock = self.info.lock().unwrap();
+        let mut bots = info_lock.bots.iter().cloned().collect::<Vec<i64>>();
+        bots.sort();
+
+        // bots draw from the game rng so a replay makes the same moves
+        let mut rng = info_lock.rng.clone();
+        let mut wolf_target = None;
+        for bot in bots {
+            let plan =
+                bot::plan(&mut info_lock, bot, &mut wolf_target, &mut rng);
+            let (channel, cmd) = match plan {
+                Some(plan) => plan,
+                None => continue,
+            };
+            let channel_id = match info_lock.channels.get(&channel) {
+                Some(&channel_id) => channel_id,
+                None => continue,
+            };
+
+            let addr = self.addr.clone();
+            let message = format!("{}{}", bot_prefix, cmd);
+            let delay = rand::thread_rng().gen_range(3..15);
+            Arbiter::spawn(async move {
+                actix::clock::delay_for(Duration::from_secs(delay)).await;
+                addr.do_send(BotPlay {
+                    user_id: bot,
+                    channel_id,
+                    message,
+                });
+            });
+        }
+        info_lock.rng = rng;
+    }
+
+    fn start_timmer(&self, resumed: bool) {
         let addr = self.addr.clone();
         let info = self.info.clone();
 
         let is_day = info.lock().unwrap().is_day;
         let num_day = info.lock().unwrap().num_day;
-        let (daytime, nighttime, preiod) = info.lock().unwrap().timmer;
+        let settings = info.lock().unwrap().settings.clone();
+        let preiod = settings.period;
+        let (slow, remind) = (settings.slow_mode, settings.slow_remind * 60);
         let next = info.lock().unwrap().next_flag.clone();
 
         let gameplay = *info
@@ -437,11 +809,40 @@ impl GameLoop {
             .get(&GameChannel::GamePlay)
             .unwrap();
 
-        let timecount = if is_day { daytime } else { nighttime };
+        let timecount = settings.phase_time(is_day);
+        let mut info_lock = info.lock().unwrap();
+        // a deadline left over from before a restart wins over a fresh one,
+        // and a game paused before it stays paused
+        info_lock.timer = match info_lock.phase_deadline.take() {
+            Some(deadline) => PhaseTimer::until(deadline, timecount),
+            None if resumed && info_lock.timer.is_paused() => {
+                info_lock.timer.clone()
+            }
+            None => PhaseTimer::new(timecount),
+        };
+        drop(info_lock);
+        self.save_deadline();
+        let game_id = self.id;
+
+        if slow && info.lock().unwrap().narrator.is_none() {
+            let timer = info.lock().unwrap().timer.clone();
+            addr.do_send(BotMsg {
+                channel_id: gameplay,
+                msg: ttp::phase_deadline(
+                    timer.remaining_secs(),
+                    timer.deadline_unix(),
+                ),
+                reply_to: None,
+            });
+        }
+        // hours long phases don't need sub second precision
+        let tick = if slow { 5000 } else { 250 };
 
         let fut = async move {
-            for count in (1..timecount + 1).rev() {
-                {
+            let mut last = None;
+            let mut reminded = false;
+            loop {
+                let (count, total) = {
                     let lock = info.lock().unwrap();
                     if lock.is_ended
                         || lock.is_stopped
@@ -450,17 +851,58 @@ impl GameLoop {
                     {
                         return;
                     }
+                    if lock.timer.is_paused() || lock.narrator.is_some() {
+                        (None, 0)
+                    } else {
+                        let timer = &lock.timer;
+                        (Some(timer.remaining_secs()), timer.total_secs())
+                    }
+                };
+
+                match count {
+                    Some(0) => break,
+                    Some(count) if slow => {
+                        // an extension past the reminder arms it again
+                        if count > remind {
+                            reminded = false;
+                        }
+                        if reminded || count > remind {
+                            actix::clock::delay_for(Duration::from_millis(
+                                tick,
+                            ))
+                            .await;
+                            continue;
+                        }
+                        reminded = true;
+                        let pending = get_pending(&info.lock().unwrap());
+                        addr.do_send(BotMsg {
+                            channel_id: gameplay,
+                            msg: ttp::phase_reminder(count, &pending),
+                            reply_to: None,
+                        });
+                        addr.do_send(GameMsg {
+                            game_id,
+                            event: GameEvent::Timer(count, total, false),
+                        });
+                    }
+                    Some(count) if last != Some(count) => {
+                        last = Some(count);
+                        if count % preiod == 0 || count <= 5 {
+                            addr.do_send(BotMsg {
+                                channel_id: gameplay,
+                                msg: ttp::timeout(count),
+                                reply_to: None,
+                            });
+                            addr.do_send(GameMsg {
+                                game_id,
+                                event: GameEvent::Timer(count, total, false),
+                            });
+                        }
+                    }
+                    _ => {}
                 }
 
-                if count % preiod == 0 || count <= 5 {
-                    addr.do_send(BotMsg {
-                        channel_id: gameplay,
-                        msg: ttp::timeout(count),
-                        reply_to: None,
-                    });
-                }
-
-                actix::clock::delay_for(Duration::from_secs(1)).await;
+                actix::clock::delay_for(Duration::from_millis(tick)).await;
             }
 
             next.wake();
@@ -561,9 +1003,44 @@ impl GameLoop {
         self.addr.do_send(UpdatePers(user_id));
     }
 
-    fn update_win(&self, user_id: i64, is_winner: bool) {
+    fn update_win(
+        &self,
+        user_id: i64,
+        is_winner: bool,
+        mode: &Option<(String, u32)>,
+    ) {
         let conn = get_conn(self.db_pool.clone());
         db::user::update_win(&conn, user_id, is_winner).ok();
+        if let Some((mode, version)) = mode {
+            db::user::update_mode_win(
+                &conn, user_id, mode, *version, is_winner,
+            )
+            .ok();
+        }
+    }
+}
+
+// players the phase is still waiting on, for the slow mode reminder
+fn get_pending(info: &GameInfo) -> Vec<i64> {
+    let mut pending = if info.is_day {
+        let (alive, _) = info.get_alives();
+        alive
+            .into_iter()
+            .filter(|uid| !info.vote_kill.contains_key(uid))
+            .collect::<Vec<i64>>()
+    } else {
+        info.night_pending_actions.iter().cloned().collect()
+    };
+    pending.sort();
+    pending
+}
+
+fn owe_night_action(player: &mut dyn Player) -> bool {
+    match player.get_role_name() {
+        roles::WEREWOLF | roles::SUPERWOLF | roles::GUARD | roles::SEER => true,
+        roles::WITCH => player.get_power() || player.get_power2(),
+        roles::CUPID => player.get_power(),
+        _ => false,
     }
 }
 
@@ -1,7 +1,14 @@
+pub mod bot;
 pub mod characters;
 pub mod cmds;
+#[cfg(test)]
+pub mod fixtures;
 pub mod game;
 pub mod game_loop;
 pub mod next;
+pub mod pseudonym;
+pub mod settings;
+pub mod snapshot;
 pub mod text_templates;
+pub mod timer;
 pub use game::Game;
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::ws::cmd_parser::{Cmd, GameEvent, NightResult};
+
+const ANIMALS: [&str; 16] = [
+    "Cáo", "Sói", "Gấu", "Hổ", "Báo", "Cú", "Quạ", "Nai", "Thỏ", "Rắn", "Chồn",
+    "Khỉ", "Dơi", "Ưng", "Sẻ", "Rùa",
+];
+
+const COLORS: [&str; 8] =
+    ["Xám", "Đỏ", "Trắng", "Đen", "Vàng", "Xanh", "Nâu", "Bạc"];
+
+const AVATARS: [&str; 16] = [
+    "🦊", "🐺", "🐻", "🐯", "🐆", "🦉", "🐦", "🦌", "🐰", "🐍", "🦡", "🐒",
+    "🦇", "🦅", "🐤", "🐢",
+];
+
+// identity shown in place of a player of an anonymous game
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Pseudonym {
+    pub id: i64,
+    pub name: String,
+    pub avatar: String,
+}
+
+// distinct names for `ids.len()` players, each paired with a fresh id
+pub fn gen_pseudonyms<R: Rng>(ids: &[i64], rng: &mut R) -> Vec<Pseudonym> {
+    let mut animals = (0..ANIMALS.len()).collect::<Vec<usize>>();
+    animals.shuffle(rng);
+
+    ids.iter()
+        .zip(animals.iter().cycle())
+        .enumerate()
+        .map(|(i, (&id, &animal))| {
+            let color = COLORS[rng.gen_range(0..COLORS.len())];
+            let mut name = format!("{} {}", ANIMALS[animal], color);
+            // more players than animals, keep the names apart
+            if i >= ANIMALS.len() {
+                name += format!(" {}", i / ANIMALS.len() + 1).as_str();
+            }
+            Pseudonym {
+                id,
+                name,
+                avatar: AVATARS[animal].to_string(),
+            }
+        })
+        .collect()
+}
+
+// real to pseudonym ids of an anonymous game, or the other way round
+pub type Masks = HashMap<i64, i64>;
+
+// the viewer always reads their own id
+fn mask_id(id: &mut String, masks: &Masks, viewer: i64) {
+    if let Some(masked) = id
+        .parse::<i64>()
+        .ok()
+        .filter(|&id| id != viewer)
+        .and_then(|id| masks.get(&id))
+    {
+        *id = masked.to_string();
+    }
+}
+
+// rewrites the `<@id>` mentions of a chat message
+pub fn mask_mentions(text: &str, masks: &Masks, viewer: i64) -> String {
+    let mut masked = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<@") {
+        masked.push_str(&rest[..start + 2]);
+        rest = &rest[start + 2..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let mut id = rest[..end].to_string();
+        if rest[end..].starts_with('>') {
+            mask_id(&mut id, masks, viewer);
+        }
+        masked.push_str(&id);
+        rest = &rest[end..];
+    }
+    masked.push_str(rest);
+    masked
+}
+
+// rewrites the user ids of a command as `viewer` should read them
+pub fn mask_cmd(cmd: &mut Cmd, masks: &Masks, viewer: i64) {
+    match cmd {
+        Cmd::BroadCastMsg {
+            user_id, message, ..
+        } => {
+            mask_id(user_id, masks, viewer);
+            *message = mask_mentions(message, masks, viewer);
+        }
+        Cmd::GetMsgRes { messages, .. } => {
+            for msg in messages.iter_mut() {
+                mask_id(&mut msg.user_id, masks, viewer);
+                msg.message = mask_mentions(&msg.message, masks, viewer);
+            }
+        }
+        Cmd::GameEvent(event) => mask_event(event, masks, viewer),
+        _ => {}
+    }
+}
+
+pub fn mask_event(event: &mut GameEvent, masks: &Masks, viewer: i64) {
+    fn mask_keys<V>(map: &mut HashMap<String, V>, masks: &Masks, viewer: i64) {
+        *map = map
+            .drain()
+            .map(|(mut id, v)| {
+                mask_id(&mut id, masks, viewer);
+                (id, v)
+            })
+            .collect();
+    }
+    let mask = |id: &mut String| mask_id(id, masks, viewer);
+    match event {
+        GameEvent::UserJoin(id)
+        | GameEvent::UserLeave(id)
+        | GameEvent::UserKicked(id)
+        | GameEvent::UserStart(id)
+        | GameEvent::UserStop(id)
+        | GameEvent::UserNext(id)
+        | GameEvent::UserUnvote(id)
+        | GameEvent::NewHost(Some(id))
+        | GameEvent::NewNarrator(Some(id))
+        | GameEvent::PlayerDied(id, ..)
+        | GameEvent::PlayerReborn(id)
+        | GameEvent::PlayerAway(id)
+        | GameEvent::PlayerBack(id)
+        | GameEvent::PlayerAfk(id) => mask(id),
+        GameEvent::UserVote { user_id, vote_for } => {
+            mask(user_id);
+            mask(vote_for);
+        }
+        GameEvent::PlayerSubstituted(old, new) => {
+            mask(old);
+            mask(new);
+        }
+        GameEvent::VoteTally(tally) => mask_keys(tally, masks, viewer),
+        GameEvent::VoteRecord(_, votes) => {
+            votes.values_mut().for_each(mask);
+            mask_keys(votes, masks, viewer);
+        }
+        GameEvent::NightResult(id, result) => {
+            mask(id);
+            match result {
+                NightResult::GuardSaved(target)
+                | NightResult::GuardQuiet(target)
+                | NightResult::WolfKilled(target)
+                | NightResult::WolfBlocked(target)
+                | NightResult::CurseLanded(target)
+                | NightResult::CurseBlocked(target) => mask(target),
+            }
+        }
+        GameEvent::GameHistory(events) => events
+            .iter_mut()
+            .for_each(|event| mask_event(event, masks, viewer)),
+        GameEvent::Seats(ids) => ids.iter_mut().for_each(mask),
+        GameEvent::Pseudonyms(names) => mask_keys(names, masks, viewer),
+        _ => {}
+    }
+}
@@ -0,0 +1,480 @@
+use std::{collections::HashMap, fs::read_to_string};
+
+use serde::{Deserialize, Serialize};
+
+use super::characters::roles;
+use super::text_templates as ttp;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RevealRole {
+    Never,
+    Always,
+    Lynched,
+}
+
+impl std::str::FromStr for RevealRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(Self::Never),
+            "always" => Ok(Self::Always),
+            "lynched" => Ok(Self::Lynched),
+            _ => Err(ttp::invalid_value(s)),
+        }
+    }
+}
+
+impl std::fmt::Display for RevealRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Never => write!(f, "never"),
+            Self::Always => write!(f, "always"),
+            Self::Lynched => write!(f, "lynched"),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Mode {
+    #[serde(default)]
+    pub settings: HashMap<String, String>,
+    #[serde(default)]
+    pub deck: Vec<String>,
+    #[serde(default)]
+    pub weights: HashMap<String, f32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ModeConfig {
+    pub version: u32,
+    pub modes: HashMap<String, Mode>,
+    // fixed decks for `roles preset`, apart from the modes
+    #[serde(default)]
+    pub decks: HashMap<String, Vec<String>>,
+}
+
+impl ModeConfig {
+    pub fn load() -> Result<Self, String> {
+        let json = read_to_string("./jsons/modes.json")
+            .map_err(|err| err.to_string())?;
+        serde_json::from_str(&json).map_err(|err| err.to_string())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names =
+            self.modes.keys().map(|k| k.as_str()).collect::<Vec<&str>>();
+        names.sort();
+        names
+    }
+
+    pub fn deck_names(&self) -> Vec<&str> {
+        let mut names =
+            self.decks.keys().map(|k| k.as_str()).collect::<Vec<&str>>();
+        names.sort();
+        names
+    }
+}
+
+// what happens to players who stay away past the grace window
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AfkPolicy {
+    Wait,
+    // their night action is skipped
+    SkipAction,
+    // they count as having voted `next`
+    AutoNext,
+}
+
+impl std::str::FromStr for AfkPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wait" => Ok(Self::Wait),
+            "skip_action" => Ok(Self::SkipAction),
+            "auto_next" => Ok(Self::AutoNext),
+            _ => Err(ttp::invalid_value(s)),
+        }
+    }
+}
+
+impl std::fmt::Display for AfkPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Wait => write!(f, "wait"),
+            Self::SkipAction => write!(f, "skip_action"),
+            Self::AutoNext => write!(f, "auto_next"),
+        }
+    }
+}
+
+pub const MIN_PLAYERS: usize = 4;
+pub const MAX_PLAYERS: usize = 16;
+// vote threshold standing for an exact two thirds of the players
+pub const TWO_THIRDS: usize = 66;
+
+// vote thresholds are percents of the players in game
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GameSettings {
+    pub day_time: u64,
+    pub night_time: u64,
+    pub period: u64,
+    pub min_players: usize,
+    pub max_players: usize,
+    pub start_vote: usize,
+    pub stop_vote: usize,
+    pub next_vote: usize,
+    pub pause_vote: usize,
+    pub reveal_role: RevealRole,
+    pub witch_both: bool,
+    pub hide_roles: bool,
+    // lobby size that triggers the countdown, 0 disables it
+    pub auto_start: usize,
+    pub auto_start_time: u64,
+    pub spectate_cemetery: bool,
+    pub away_grace: u64,
+    pub afk_policy: AfkPolicy,
+    // phases without activity before a warning and before death, 0 disables
+    pub idle_warn: u16,
+    pub idle_kill: u16,
+    // slow games count phases in hours and remind players before the end
+    pub slow_mode: bool,
+    pub slow_day: u64,
+    pub slow_night: u64,
+    // minutes before the deadline, 0 disables the reminder
+    pub slow_remind: u64,
+    // players only see each other's pseudonyms until the game ends
+    pub anonymous: bool,
+    // unranked games leave the stats alone
+    pub ranked: bool,
+    // replays the deal and every draw of an earlier game, 0 picks a new seed,
+    // only unranked games may pick one
+    pub seed: u64,
+    pub deck: Vec<String>,
+    pub weights: HashMap<String, f32>,
+    // name and version of the mode preset the settings came from
+    pub mode: Option<(String, u32)>,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            day_time: 180,
+            night_time: 60,
+            period: 30,
+            min_players: MIN_PLAYERS,
+            max_players: MAX_PLAYERS,
+            start_vote: TWO_THIRDS,
+            stop_vote: TWO_THIRDS,
+            next_vote: TWO_THIRDS,
+            pause_vote: TWO_THIRDS,
+            reveal_role: RevealRole::Never,
+            witch_both: false,
+            hide_roles: false,
+            auto_start: 0,
+            auto_start_time: 30,
+            spectate_cemetery: false,
+            away_grace: 60,
+            afk_policy: AfkPolicy::SkipAction,
+            idle_warn: 0,
+            idle_kill: 0,
+            slow_mode: false,
+            slow_day: 12,
+            slow_night: 6,
+            slow_remind: 60,
+            anonymous: false,
+            ranked: true,
+            seed: 0,
+            deck: Vec::new(),
+            weights: HashMap::new(),
+            mode: None,
+        }
+    }
+}
+
+impl GameSettings {
+    pub const KEYS: [&'static str; 26] = [
+        "day_time",
+        "night_time",
+        "period",
+        "min_players",
+        "max_players",
+        "start_vote",
+        "stop_vote",
+        "next_vote",
+        "pause_vote",
+        "reveal",
+        "witch_both",
+        "hide_roles",
+        "auto_start",
+        "auto_start_time",
+        "spectate_cemetery",
+        "away_grace",
+        "afk_policy",
+        "idle_warn",
+        "idle_kill",
+        "slow_mode",
+        "slow_day",
+        "slow_night",
+        "slow_remind",
+        "anonymous",
+        "ranked",
+        "seed",
+    ];
+
+    pub fn from_json(json: &str) -> Self {
+        serde_json::from_str(json).unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    // length of a phase in seconds
+    pub fn phase_time(&self, is_day: bool) -> u64 {
+        match (self.slow_mode, is_day) {
+            (true, true) => self.slow_day * 3600,
+            (true, false) => self.slow_night * 3600,
+            (false, true) => self.day_time,
+            (false, false) => self.night_time,
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let mut s = self.clone();
+        match key {
+            "day_time" => s.day_time = parse_range(value, 10, 3600)?,
+            "night_time" => s.night_time = parse_range(value, 10, 3600)?,
+            "period" => s.period = parse_range(value, 5, 600)?,
+            "min_players" => {
+                s.min_players = parse_range(value, MIN_PLAYERS, MAX_PLAYERS)?
+            }
+            "max_players" => {
+                s.max_players = parse_range(value, MIN_PLAYERS, MAX_PLAYERS)?
+            }
+            "start_vote" => s.start_vote = parse_range(value, 1, 100)?,
+            "stop_vote" => s.stop_vote = parse_range(value, 1, 100)?,
+            "next_vote" => s.next_vote = parse_range(value, 1, 100)?,
+            "pause_vote" => s.pause_vote = parse_range(value, 1, 100)?,
+            "reveal" => s.reveal_role = value.parse()?,
+            "witch_both" => {
+                s.witch_both =
+                    value.parse().map_err(|_| ttp::invalid_value(value))?
+            }
+            "hide_roles" => {
+                s.hide_roles =
+                    value.parse().map_err(|_| ttp::invalid_value(value))?
+            }
+            "spectate_cemetery" => {
+                s.spectate_cemetery =
+                    value.parse().map_err(|_| ttp::invalid_value(value))?
+            }
+            "away_grace" => s.away_grace = parse_range(value, 10, 3600)?,
+            "afk_policy" => s.afk_policy = value.parse()?,
+            "idle_warn" => s.idle_warn = parse_range(value, 0, 20)?,
+            "idle_kill" => s.idle_kill = parse_range(value, 0, 20)?,
+            "slow_mode" => {
+                s.slow_mode =
+                    value.parse().map_err(|_| ttp::invalid_value(value))?
+            }
+            "anonymous" => {
+                s.anonymous =
+                    value.parse().map_err(|_| ttp::invalid_value(value))?
+            }
+            "ranked" => {
+                s.ranked =
+                    value.parse().map_err(|_| ttp::invalid_value(value))?
+            }
+            "seed" => {
+                s.seed = value.parse().map_err(|_| ttp::invalid_value(value))?
+            }
+            "slow_day" => s.slow_day = parse_range(value, 1, 72)?,
+            "slow_night" => s.slow_night = parse_range(value, 1, 72)?,
+            "slow_remind" => s.slow_remind = parse_range(value, 0, 720)?,
+            "auto_start" => s.auto_start = parse_range(value, 0, 16)?,
+            "auto_start_time" => {
+                s.auto_start_time = parse_range(value, 5, 600)?
+            }
+            _ => return Err(ttp::invalid_setting_key(key, &Self::KEYS)),
+        }
+        // the settings no longer match the preset they came from
+        s.mode = None;
+
+        if s.auto_start != 0
+            && (s.auto_start < s.min_players || s.auto_start > s.max_players)
+        {
+            return Err(ttp::out_of_range(s.min_players, s.max_players));
+        }
+
+        if s.ranked && s.seed != 0 {
+            return Err(ttp::seed_in_ranked());
+        }
+
+        if s.min_players > s.max_players {
+            return Err(ttp::invalid_player_limits(
+                s.min_players,
+                s.max_players,
+            ));
+        }
+
+        *self = s;
+        Ok(())
+    }
+
+    // a mode resets everything but the player limits of the room
+    pub fn set_mode(&mut self, name: &str) -> Result<(), String> {
+        let config = ModeConfig::load()?;
+        let mode = config
+            .modes
+            .get(name)
+            .ok_or(ttp::mode_not_found(name, &config.names()))?;
+
+        let mut s = Self {
+            min_players: self.min_players,
+            max_players: self.max_players,
+            ..Self::default()
+        };
+        for (key, value) in mode.settings.iter() {
+            s.set(key, value)?;
+        }
+        for role in mode.deck.iter() {
+            s.add_role(role)?;
+        }
+        for (role, &weight) in mode.weights.iter() {
+            let role = roles::parse(role).ok_or(ttp::role_not_found(role))?;
+            if weight <= 0.0 {
+                return Err(ttp::invalid_value(&weight.to_string()));
+            }
+            s.weights.insert(role.to_string(), weight);
+        }
+        s.mode = Some((name.to_string(), config.version));
+
+        *self = s;
+        Ok(())
+    }
+
+    pub fn add_role(&mut self, name: &str) -> Result<&'static str, String> {
+        let role = roles::parse(name).ok_or(ttp::role_not_found(name))?;
+        if !roles::STACKABLE.contains(&role)
+            && self.deck.iter().any(|r| r == role)
+        {
+            return Err(ttp::role_is_unique(role));
+        }
+        if self.deck.len() >= self.max_players {
+            return Err(ttp::deck_too_large(self.deck.len(), self.max_players));
+        }
+        self.deck.push(role.to_string());
+        self.mode = None;
+        Ok(role)
+    }
+
+    pub fn remove_role(&mut self, name: &str) -> Result<&'static str, String> {
+        let role = roles::parse(name).ok_or(ttp::role_not_found(name))?;
+        let index = self
+            .deck
+            .iter()
+            .position(|r| r == role)
+            .ok_or(ttp::role_not_in_deck(role))?;
+        self.deck.remove(index);
+        self.mode = None;
+        Ok(role)
+    }
+
+    // takes only the deck, the other settings are kept
+    pub fn set_preset(&mut self, name: &str) -> Result<(), String> {
+        let config = ModeConfig::load()?;
+        let deck = config
+            .decks
+            .get(name)
+            .ok_or(ttp::preset_not_found(name, &config.deck_names()))?;
+
+        let mut s = self.clone();
+        s.deck.clear();
+        for role in deck.iter() {
+            s.add_role(role)?;
+        }
+        self.deck = s.deck;
+        self.mode = None;
+        Ok(())
+    }
+
+    pub fn check_deck(&self, numplayer: usize) -> Result<(), String> {
+        if self.deck.is_empty() {
+            return Ok(());
+        }
+        if self.deck.len() > numplayer {
+            return Err(ttp::deck_too_large(self.deck.len(), numplayer));
+        }
+        let wolves = self.deck.iter().filter(|r| roles::is_wolf(r)).count();
+        if wolves == 0 || wolves * 2 >= numplayer {
+            return Err(ttp::invalid_wolves(wolves, numplayer));
+        }
+        Ok(())
+    }
+}
+
+pub fn is_enough_vote(
+    percent: usize,
+    numvote: usize,
+    numplayer: usize,
+) -> bool {
+    if percent == TWO_THIRDS {
+        return numvote * 3 >= numplayer * 2;
+    }
+    numvote * 100 >= numplayer * percent
+}
+
+fn parse_range<T>(value: &str, min: T, max: T) -> Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+{
+    let v = value.parse::<T>().map_err(|_| ttp::invalid_value(value))?;
+    if v < min || v > max {
+        return Err(ttp::out_of_range(min, max));
+    }
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_preset_takes_only_the_deck() {
+        let mut s = GameSettings::default();
+        s.set("day_time", "60").unwrap();
+        s.set_preset("classic").unwrap();
+        assert_eq!(
+            s.deck,
+            vec![
+                roles::WEREWOLF,
+                roles::WEREWOLF,
+                roles::SEER,
+                roles::GUARD,
+                roles::WITCH
+            ]
+        );
+        assert_eq!(s.day_time, 60);
+        assert!(s.set_preset("quick").is_err());
+    }
+
+    #[test]
+    fn afk_policy_round_trips() {
+        let mut s = GameSettings::default();
+        for name in ["wait", "skip_action", "auto_next"] {
+            s.set("afk_policy", name).unwrap();
+            assert_eq!(s.afk_policy.to_string(), name);
+        }
+        assert!(s.set("afk_policy", "skip").is_err());
+    }
+
+    #[test]
+    fn decks_are_not_modes() {
+        let config = ModeConfig::load().unwrap();
+        assert!(config.deck_names().contains(&"basic"));
+        assert!(!config.names().contains(&"basic"));
+        assert!(GameSettings::default().set_mode("basic").is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+
+use actix::Addr;
+use serde::{Deserialize, Serialize};
+
+use crate::ws::cmd_parser::{DeathCause, GameEvent};
+use crate::ws::ChatServer;
+
+use super::characters::{self, player::PlayerStatus};
+use super::game::{GameChannel, GameInfo};
+use super::pseudonym::Pseudonym;
+use super::timer::PhaseTimer;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayerSnapshot {
+    pub role: String,
+    pub channel_id: i64,
+    pub status: PlayerStatus,
+    pub power: bool,
+    pub power2: bool,
+    pub mana: bool,
+}
+
+// everything a running game needs to carry on after a restart, the lobby
+// part of GameInfo is rebuilt from the games tables
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Snapshot {
+    // enum keys don't make json object keys
+    pub channels: Vec<(GameChannel, i64)>,
+    pub players: HashMap<i64, PlayerSnapshot>,
+    pub seats: Vec<i64>,
+    pub is_ended: bool,
+    pub is_day: bool,
+    pub num_day: u16,
+    // the phase was announced and its start effects already applied
+    pub phase_started: bool,
+
+    pub vote_kill: HashMap<i64, i64>,
+    pub wolf_kill: HashMap<i64, i64>,
+    pub cupid_couple: HashMap<i64, i64>,
+    pub night_pending_kill: HashMap<i64, DeathCause>,
+    pub night_pending_actions: HashSet<i64>,
+    pub vote_nexts: HashSet<i64>,
+    pub guard_yesterday_target: Option<(i64, u16)>,
+    pub witch_reborn: Option<i64>,
+    pub witch_curse: Option<(i64, i64)>,
+
+    pub spectators: HashSet<i64>,
+    pub away: HashMap<i64, u64>,
+    pub away_gen: u64,
+    pub afk: HashSet<i64>,
+    pub vote_stops: HashSet<i64>,
+    pub vote_pauses: HashSet<i64>,
+    // remaining and total seconds of a paused phase, a running one comes
+    // back from the stored deadline
+    pub paused_timer: Option<(u64, u64)>,
+    pub idle_phases: HashMap<i64, u16>,
+    pub sub_requests: HashMap<i64, i64>,
+    pub narrator: Option<i64>,
+    pub overrides: HashMap<i64, bool>,
+    pub pseudonyms: HashMap<i64, Pseudonym>,
+    pub bot_notes: HashMap<i64, HashMap<i64, bool>>,
+    pub seed: u64,
+    pub history: Vec<GameEvent>,
+}
+
+impl Snapshot {
+    pub fn take(info: &mut GameInfo) -> Self {
+        Self {
+            channels: info.channels.iter().map(|(&c, &id)| (c, id)).collect(),
+            players: info
+                .players
+                .iter_mut()
+                .map(|(&uid, player)| {
+                    let snapshot = PlayerSnapshot {
+                        role: player.get_role_name().to_string(),
+                        channel_id: *player.get_channelid(),
+                        status: *player.get_status(),
+                        power: player.get_power(),
+                        power2: player.get_power2(),
+                        mana: player.get_mana(),
+                    };
+                    (uid, snapshot)
+                })
+                .collect(),
+            seats: info.seats.clone(),
+            is_ended: info.is_ended,
+            is_day: info.is_day,
+            num_day: info.num_day,
+            phase_started: info.phase_started,
+
+            vote_kill: info.vote_kill.clone(),
+            wolf_kill: info.wolf_kill.clone(),
+            cupid_couple: info.cupid_couple.clone(),
+            night_pending_kill: info.night_pending_kill.clone(),
+            night_pending_actions: info.night_pending_actions.clone(),
+            vote_nexts: info.vote_nexts.clone(),
+            guard_yesterday_target: info.guard_yesterday_target,
+            witch_reborn: info.witch_reborn,
+            witch_curse: info.witch_curse,
+
+            spectators: info.spectators.clone(),
+            away: info.away.clone(),
+            away_gen: info.away_gen,
+            afk: info.afk.clone(),
+            vote_stops: info.vote_stops.clone(),
+            vote_pauses: info.vote_pauses.clone(),
+            paused_timer: Some(&info.timer)
+                .filter(|timer| timer.is_paused())
+                .map(|timer| (timer.remaining_secs(), timer.total_secs())),
+            idle_phases: info.idle_phases.clone(),
+            sub_requests: info.sub_requests.clone(),
+            narrator: info.narrator,
+            overrides: info.overrides.clone(),
+            pseudonyms: info.pseudonyms.clone(),
+            bot_notes: info.bot_notes.clone(),
+            seed: info.seed,
+            history: info.history.clone(),
+        }
+    }
+
+    pub fn restore(
+        self,
+        info: &mut GameInfo,
+        addr: Addr<ChatServer>,
+    ) -> Result<(), String> {
+        for (uid, p) in self.players {
+            let mut player = characters::new_role(&p.role, uid, addr.clone())?;
+            *player.get_channelid() = p.channel_id;
+            *player.get_status() = p.status;
+            player.set_power(p.power);
+            player.set_power2(p.power2);
+            player.set_mana(p.mana);
+            info.players.insert(uid, player);
+        }
+
+        info.channels = self.channels.into_iter().collect();
+        info.seats = self.seats;
+        info.is_started = true;
+        info.is_ended = self.is_ended;
+        info.is_day = self.is_day;
+        info.num_day = self.num_day;
+        info.phase_started = self.phase_started;
+
+        info.vote_kill = self.vote_kill;
+        info.wolf_kill = self.wolf_kill;
+        info.cupid_couple = self.cupid_couple;
+        info.night_pending_kill = self.night_pending_kill;
+        info.night_pending_actions = self.night_pending_actions;
+        info.vote_nexts = self.vote_nexts;
+        info.guard_yesterday_target = self.guard_yesterday_target;
+        info.witch_reborn = self.witch_reborn;
+        info.witch_curse = self.witch_curse;
+
+        info.spectators = self.spectators;
+        info.away = self.away;
+        info.away_gen = self.away_gen;
+        info.afk = self.afk;
+        info.vote_stops = self.vote_stops;
+        info.vote_pauses = self.vote_pauses;
+        if let Some((remaining, total)) = self.paused_timer {
+            info.timer = PhaseTimer::paused(remaining, total);
+        }
+        info.idle_phases = self.idle_phases;
+        info.sub_requests = self.sub_requests;
+        info.narrator = self.narrator;
+        info.overrides = self.overrides;
+        info.pseudonyms = self.pseudonyms;
+        info.bot_notes = self.bot_notes;
+        info.seed = self.seed;
+        info.history = self.history;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::super::characters::roles;
+    use super::super::fixtures::{addr, lobby, seat, DEAL};
+    use super::*;
+
+    fn to_value(info: &mut GameInfo) -> Value {
+        serde_json::to_value(Snapshot::take(info)).unwrap()
+    }
+
+    #[test]
+    fn take_and_restore_round_trip() {
+        let addr = addr();
+        let mut info = lobby(DEAL.len());
+        seat(&mut info, &DEAL, &addr);
+        info.players.get_mut(&6).unwrap().set_power2(false);
+        info.seats = vec![3, 1, 6, 4, 2, 5];
+        info.is_started = true;
+        info.num_day = 2;
+        info.phase_started = true;
+        info.vote_kill.insert(1, 4);
+        info.away.insert(2, 1);
+        info.away_gen = 1;
+        info.afk.insert(2);
+        info.vote_stops.insert(4);
+        info.vote_pauses.insert(3);
+        info.timer.pause();
+        info.seed = 42;
+
+        let json = serde_json::to_string(&Snapshot::take(&mut info)).unwrap();
+        let snapshot = serde_json::from_str::<Snapshot>(&json).unwrap();
+        let mut restored = lobby(DEAL.len());
+        snapshot.restore(&mut restored, addr).unwrap();
+
+        assert_eq!(to_value(&mut restored), to_value(&mut info));
+        assert!(restored.timer.is_paused());
+        assert_eq!(
+            restored.players.get_mut(&2).unwrap().get_role_name(),
+            roles::SEER
+        );
+    }
+}
@@ -1,6 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+use crate::models::game::GameDisplay;
+use crate::ws::cmd_parser::{DeathCause, NightResult};
 
 use super::characters::player::Player;
+use super::pseudonym::Pseudonym;
+use super::settings::{AfkPolicy, GameSettings};
 
 pub fn not_in_game() -> String {
     format!("Bạn đang không ở trong game.")
@@ -10,6 +15,77 @@ pub fn in_other_game() -> String {
     format!("Bạn đang trong trò chơi khác.")
 }
 
+pub fn room_existed(name: &str) -> String {
+    format!("Phòng `{}` đã tồn tại.", name)
+}
+
+pub fn room_not_found(name: &str) -> String {
+    format!("Không tìm thấy phòng `{}`.", name)
+}
+
+pub fn no_room(prefix: &str) -> String {
+    format!(
+        "Hiện không có phòng nào đang chờ, hãy tạo phòng mới với `{}create <name>`.",
+        prefix
+    )
+}
+
+pub fn room_list(rooms: &[GameDisplay]) -> String {
+    if rooms.is_empty() {
+        return "Hiện chưa có phòng nào.".to_string();
+    }
+
+    let mut s = "Danh sách phòng:\n".to_string();
+    s += rooms
+        .iter()
+        .map(|r| {
+            format!("- `{}`: {} người chơi, {}", r.name, r.num_players, r.state)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+        .as_str();
+    s
+}
+
+pub fn private_room_created() -> String {
+    "Đã tạo phòng riêng, mã tham gia được gửi trong kênh của phòng.".to_string()
+}
+
+pub fn join_code(prefix: &str, code: &str) -> String {
+    format!(
+        "Đây là phòng riêng, mã tham gia là `{}`. Người chơi khác có thể dùng `{}join {}` hoặc được mời bằng `{}invite @người chơi`.",
+        code, prefix, code, prefix
+    )
+}
+
+pub fn user_invited(user_id: i64) -> String {
+    format!("Đã gửi lời mời đến <@{}>.", user_id)
+}
+
+pub fn player_in_game(user_id: i64) -> String {
+    format!("<@{}> đã ở trong trò chơi rồi.", user_id)
+}
+
+pub fn must_in_gameplay() -> String {
+    "Hãy dùng lệnh này trong kênh của phòng.".to_string()
+}
+
+pub fn user_spectate(user_id: i64) -> String {
+    format!("<@{}> đang theo dõi trò chơi.", user_id)
+}
+
+pub fn user_unspectate(user_id: i64) -> String {
+    format!("<@{}> đã ngừng theo dõi trò chơi.", user_id)
+}
+
+pub fn aready_spectating() -> String {
+    "Bạn đang theo dõi một trò chơi rồi.".to_string()
+}
+
+pub fn not_spectating() -> String {
+    "Bạn đang không theo dõi trò chơi nào.".to_string()
+}
+
 pub fn game_is_started() -> String {
     format!("Trò chơi đã bắt đầu rồi.")
 }
@@ -39,7 +115,7 @@ pub fn not_enough_player(numplayer: usize) -> String {
 }
 
 pub fn start_game() -> String {
-    format!("2/3 người chơi đã sẵn sàng, trò chơi chuẩn bị bắt đầu.")
+    "Đã đủ người chơi sẵn sàng, trò chơi chuẩn bị bắt đầu.".to_string()
 }
 
 pub fn new_wolf(user_id: i64) -> String {
@@ -72,6 +148,29 @@ pub fn user_leave(user_id: i64, numplayer: usize) -> String {
     )
 }
 
+pub fn user_kicked(user_id: i64, numplayer: usize) -> String {
+    format!(
+        "Người chơi <@{}> đã bị mời ra khỏi trò chơi, hiện có {}.",
+        user_id, numplayer
+    )
+}
+
+pub fn new_host(user_id: i64) -> String {
+    format!("<@{}> hiện là chủ phòng.", user_id)
+}
+
+pub fn not_host() -> String {
+    "Chỉ chủ phòng mới dùng được lệnh này.".to_string()
+}
+
+pub fn kick_yourself() -> String {
+    "Bạn không thể tự mời mình ra, hãy dùng lệnh rời phòng.".to_string()
+}
+
+pub fn must_mention() -> String {
+    "Trò chơi chưa bắt đầu, hãy nhắc tên (@) người chơi.".to_string()
+}
+
 pub fn user_start(user_id: i64, numvote: usize, numplayer: usize) -> String {
     format!(
         "Người chơi <@{}> đã sằn sàng. {}/{}",
@@ -128,6 +227,47 @@ pub fn vote_kill(user_id: i64, vote_for: i64) -> String {
     )
 }
 
+pub fn unvote(user_id: i64) -> String {
+    format!("Người chơi <@{}> đã rút lại phiếu biểu quyết.", user_id)
+}
+
+pub fn not_voted() -> String {
+    "Bạn chưa biểu quyết cho ai cả.".to_string()
+}
+
+pub fn vote_tally(tally: &[(i64, u16)]) -> String {
+    if tally.is_empty() {
+        return "Chưa có ai biểu quyết.".to_string();
+    }
+
+    let mut s = "Kết quả biểu quyết hiện tại:\n".to_string();
+    s += tally
+        .iter()
+        .map(|(uid, num)| format!("<@{}>: {} phiếu", uid, num))
+        .collect::<Vec<String>>()
+        .join("\n")
+        .as_str();
+    s
+}
+
+pub fn vote_record(num_day: u16, votes: &HashMap<i64, i64>) -> String {
+    if votes.is_empty() {
+        return format!("Ngày {}, không có ai biểu quyết.", num_day);
+    }
+
+    let mut votes = votes.iter().collect::<Vec<(&i64, &i64)>>();
+    votes.sort();
+
+    let mut s = format!("Biểu quyết ngày {}:\n", num_day);
+    s += votes
+        .iter()
+        .map(|(uid, vote_for)| format!("<@{}> -> <@{}>", uid, vote_for))
+        .collect::<Vec<String>>()
+        .join("\n")
+        .as_str();
+    s
+}
+
 pub fn wrong_cmd_format(prefix: &str, s: &str) -> String {
     format!("Không đúng định dạnh lệnh, `{}{}`", prefix, s)
 }
@@ -148,7 +288,12 @@ pub fn invalid_index(from: usize, to: usize) -> String {
     format!("Giá trị không hợp lệ, chọn từ {} đến {}.", from, to)
 }
 
-pub fn player_list(list: &Vec<i64>, is_alive: bool) -> String {
+pub fn player_list(
+    list: &[i64],
+    seats: &[i64],
+    away: &HashSet<i64>,
+    is_alive: bool,
+) -> String {
     let mut s = format!(
         "Danh sách những người chơi {}:\n",
         if is_alive {
@@ -157,30 +302,27 @@ pub fn player_list(list: &Vec<i64>, is_alive: bool) -> String {
             "đã chết"
         }
     );
+    // users without a seat aren't playing, so they are left out
     s += list
         .iter()
-        .enumerate()
-        .map(|(idx, id)| format!("{}: <@{}>", idx + 1, id))
+        .filter_map(|id| {
+            let seat = seats.iter().position(|uid| uid == id)?;
+            Some(if away.contains(id) {
+                format!("{}: <@{}> (vắng mặt)", seat + 1, id)
+            } else {
+                format!("{}: <@{}>", seat + 1, id)
+            })
+        })
         .collect::<Vec<String>>()
         .join("\n")
         .as_str();
     s
 }
 
-pub fn execution(top_vote: Option<(i64, u16)>) -> String {
-    match top_vote {
-        None => format!(
-            "Không có ai bị hành hình. Trò chơi sẽ tiếp tục. Hãy cẩn thân để sống sót!
+pub fn no_execution() -> String {
+    "Không có ai bị hành hình. Trò chơi sẽ tiếp tục. Hãy cẩn thân để sống sót!
 ==========================================================================="
-        ),
-        Some((uid, votes)) => format!(
-            "Thời gian quyết định đã hết.
-Người chơi <@{}> đã bị đưa lên máy chém với số phiếu bầu là {}.
-Hy vọng tình thế của làng có thể thay đổi sau quyết định này.
-===========================================================================",
-            uid, votes
-        ),
-    }
+        .to_string()
 }
 
 pub fn new_day(bot_prefix: &str, num_day: u16) -> String {
@@ -235,25 +377,460 @@ pub fn before_wolf_action(bot_prefix: &str) -> String {
         "Đêm nay, Sói muốn lấy mạng ai? Hãy nhập `{}kill <player>` để lặng lẽ xử lý nạn nhân. Ví dụ: `{}kill 2`", bot_prefix, bot_prefix)
 }
 
-pub fn list_killed(list: &Vec<i64>) -> String {
-    let mut s = String::new();
+pub fn night_result(result: &NightResult) -> String {
+    match result {
+        NightResult::GuardSaved(uid) => format!(
+            "Đêm qua <@{}> đã bị tấn công, nhờ có bạn bảo vệ nên người đó vẫn an toàn.",
+            uid
+        ),
+        NightResult::GuardQuiet(uid) => {
+            format!("Đêm qua không có ai tấn công <@{}>.", uid)
+        }
+        NightResult::WolfKilled(uid) => {
+            format!("Sói đã xử lý thành công <@{}>.", uid)
+        }
+        NightResult::WolfBlocked(uid) => {
+            format!("Đêm qua <@{}> đã được bảo vệ, Sói không thể ra tay.", uid)
+        }
+        NightResult::CurseLanded(uid) => {
+            format!("Lời nguyền của bạn đã lấy mạng <@{}>.", uid)
+        }
+        NightResult::CurseBlocked(uid) => {
+            format!("Lời nguyền của bạn không có tác dụng với <@{}>.", uid)
+        }
+    }
+}
+
+pub fn quiet_night() -> String {
+    "Đêm qua, mọi người đều bình an.
+==========================================================================="
+        .to_string()
+}
+
+pub fn death_cause(
+    user_id: i64,
+    cause: DeathCause,
+    role: Option<&str>,
+) -> String {
+    let mut s = match cause {
+        DeathCause::Lynched => {
+            format!("<@{}> đã bị dân làng hành hình", user_id)
+        }
+        DeathCause::Wolves => format!("<@{}> đã bị Sói cắn chết", user_id),
+        DeathCause::Curse => {
+            format!("<@{}> đã chết vì lời nguyền của Phù thủy", user_id)
+        }
+        DeathCause::Grief => {
+            format!("<@{}> đã chết vì quá đau buồn khi mất người yêu", user_id)
+        }
+        DeathCause::Vision => format!(
+            "<@{}> đã bị Tiên tri nhìn thấu và không thể sống tiếp",
+            user_id
+        ),
+        DeathCause::Forest => {
+            format!(
+                "<@{}> đã đi lạc vào rừng và không bao giờ trở lại",
+                user_id
+            )
+        }
+    };
+    if let Some(role) = role {
+        s += format!(", vai của người này là {}", role).as_str();
+    }
+    s += ".";
+    s
+}
+
+pub fn settings(settings: &GameSettings) -> String {
+    format!(
+        "Cài đặt trò chơi:
+- day_time: {} giây
+- night_time: {} giây
+- period: {} giây
+- min_players: {}
+- max_players: {}
+- start_vote: {}%
+- stop_vote: {}%
+- next_vote: {}%
+- pause_vote: {}%
+- reveal: {}
+- witch_both: {}
+- hide_roles: {}
+- auto_start: {}
+- auto_start_time: {} giây
+- spectate_cemetery: {}
+- away_grace: {} giây
+- afk_policy: {} (wait | skip_action | auto_next)
+- idle_warn: {} lượt
+- idle_kill: {} lượt
+- slow_mode: {}
+- slow_day: {} giờ
+- slow_night: {} giờ
+- slow_remind: {} phút
+- anonymous: {}
+- ranked: {}
+- seed: {}
+- mode: {}",
+        settings.day_time,
+        settings.night_time,
+        settings.period,
+        settings.min_players,
+        settings.max_players,
+        settings.start_vote,
+        settings.stop_vote,
+        settings.next_vote,
+        settings.pause_vote,
+        settings.reveal_role,
+        settings.witch_both,
+        settings.hide_roles,
+        settings.auto_start,
+        settings.auto_start_time,
+        settings.spectate_cemetery,
+        settings.away_grace,
+        settings.afk_policy,
+        settings.idle_warn,
+        settings.idle_kill,
+        settings.slow_mode,
+        settings.slow_day,
+        settings.slow_night,
+        settings.slow_remind,
+        settings.anonymous,
+        settings.ranked,
+        // the seed would give the deal away
+        match settings.seed {
+            0 => "ngẫu nhiên",
+            _ => "đã đặt",
+        },
+        match &settings.mode {
+            Some((name, version)) => format!("{} (v{})", name, version),
+            None => "tùy chỉnh".to_string(),
+        }
+    )
+}
+
+pub fn deck(deck: &[String]) -> String {
+    if deck.is_empty() {
+        return "Chưa có bộ bài, nhân vật sẽ được chia ngẫu nhiên.".to_string();
+    }
+    format!(
+        "Bộ bài hiện tại ({} lá, còn lại là Villager): {}.",
+        deck.len(),
+        deck.join(", ")
+    )
+}
+
+pub fn role_not_found(name: &str) -> String {
+    format!("Không có nhân vật `{}`.", name)
+}
+
+pub fn role_is_unique(role: &str) -> String {
+    format!("Chỉ được có một {} trong bộ bài.", role)
+}
+
+pub fn role_not_in_deck(role: &str) -> String {
+    format!("Không có {} trong bộ bài.", role)
+}
+
+pub fn preset_not_found(name: &str, presets: &[&str]) -> String {
+    format!(
+        "Không có bộ bài `{}`, chọn một trong: {}.",
+        name,
+        presets.join(", ")
+    )
+}
+
+pub fn deck_too_large(numcard: usize, numplayer: usize) -> String {
+    format!(
+        "Bộ bài có {} lá, nhiều hơn số người chơi ({}).",
+        numcard, numplayer
+    )
+}
+
+pub fn invalid_wolves(numwolf: usize, numplayer: usize) -> String {
+    format!(
+        "Bộ bài có {} sói, cần ít nhất 1 và ít hơn một nửa số người chơi ({}).",
+        numwolf, numplayer
+    )
+}
+
+pub fn mode_list(names: &[&str], current: &Option<(String, u32)>) -> String {
+    format!(
+        "Chế độ hiện tại: {}. Các chế độ có sẵn: {}.",
+        match current {
+            Some((name, _)) => name.as_str(),
+            None => "tùy chỉnh",
+        },
+        names.join(", ")
+    )
+}
+
+pub fn mode_not_found(name: &str, names: &[&str]) -> String {
+    format!(
+        "Không có chế độ `{}`, chọn một trong: {}.",
+        name,
+        names.join(", ")
+    )
+}
+
+pub fn set_mode(name: &str) -> String {
+    format!("Đã chuyển sang chế độ `{}`.", name)
+}
+
+pub fn auto_start_timeout(count: u64) -> String {
+    format!("Trò chơi sẽ tự động bắt đầu sau {} giây.", count)
+}
+
+pub fn auto_start_cancelled() -> String {
+    "Đã hủy tự động bắt đầu.".to_string()
+}
+
+pub fn no_auto_start() -> String {
+    "Không có lượt đếm ngược nào để hủy.".to_string()
+}
+
+pub fn idle_warning(idle: u16, kill: u16) -> String {
+    format!(
+        "Bạn đã không hoạt động trong {} lượt, nếu tiếp tục im lặng đến lượt thứ {} bạn sẽ bị lạc vào rừng.",
+        idle, kill
+    )
+}
+
+pub fn sub_requested() -> String {
+    "Đã gửi yêu cầu thay người, hãy chờ chủ phòng hoặc người được thay đồng ý."
+        .to_string()
+}
+
+pub fn sub_request(prefix: &str, sub: i64, player: i64) -> String {
+    format!(
+        "<@{}> muốn vào thay cho <@{}>, chủ phòng hoặc <@{}> hãy dùng `{}approve <@{}>` để đồng ý.",
+        sub, player, player, prefix, sub
+    )
+}
+
+pub fn seed_in_ranked() -> String {
+    "Chỉ trận không xếp hạng (ranked false) mới được đặt seed.".to_string()
+}
+
+pub fn is_narrator() -> String {
+    "Bạn đang là người dẫn truyện, không thể chơi hay xem trận này.".to_string()
+}
+
+pub fn user_busy(user_id: i64) -> String {
+    format!(
+        "<@{}> đang chơi, xem hoặc dẫn truyện ở phòng khác.",
+        user_id
+    )
+}
+
+pub fn no_narrator() -> String {
+    "Phòng này không có người dẫn truyện.".to_string()
+}
+
+pub fn narrator_dropped() -> String {
+    "Người dẫn truyện đã rời đi, từ giờ đồng hồ sẽ điều khiển các lượt chơi."
+        .to_string()
+}
+
+pub fn sub_not_allowed() -> String {
+    "Khán giả và người dẫn truyện không thể vào thay người chơi.".to_string()
+}
+
+pub fn sub_target_present(user_id: i64) -> String {
+    format!(
+        "<@{}> vẫn đang chơi, chỉ có thể thay người đã vắng mặt.",
+        user_id
+    )
+}
+
+pub fn cannot_approve() -> String {
+    "Chỉ chủ phòng hoặc người được thay mới đồng ý được.".to_string()
+}
+
+pub fn no_sub_request(user_id: i64) -> String {
+    format!("<@{}> không có yêu cầu thay người nào.", user_id)
+}
+
+pub fn substituted(old: i64, new: i64) -> String {
+    format!("<@{}> đã vào thay cho <@{}>.", new, old)
+}
+
+pub fn player_afk(user_id: i64, policy: AfkPolicy) -> String {
+    let action = match policy {
+        AfkPolicy::AutoNext => "được tính là đã bỏ phiếu `next`",
+        _ => "sẽ được bỏ qua hành động ban đêm",
+    };
+    format!(
+        "<@{}> đã vắng mặt quá lâu, người chơi này {}.",
+        user_id, action
+    )
+}
+
+pub fn game_is_paused(prefix: &str) -> String {
+    format!(
+        "Trò chơi đang tạm dừng, hãy dùng `{}resume` để tiếp tục.",
+        prefix
+    )
+}
+
+pub fn game_not_paused() -> String {
+    "Trò chơi đang không tạm dừng.".to_string()
+}
+
+pub fn user_pause(
+    user_id: i64,
+    numvote: usize,
+    numplayer: usize,
+    resume: bool,
+) -> String {
+    format!(
+        "<@{}> muốn {} trò chơi ({}/{}).",
+        user_id,
+        if resume {
+            "tiếp tục"
+        } else {
+            "tạm dừng"
+        },
+        numvote,
+        numplayer
+    )
+}
+
+pub fn game_paused(remaining: u64) -> String {
+    format!("Trò chơi đã tạm dừng. {}", timeout(remaining))
+}
+
+pub fn game_resumed(remaining: u64) -> String {
+    format!("Trò chơi tiếp tục. {}", timeout(remaining))
+}
 
-    if list.is_empty() {
-        s += "Đêm qua, mọi người đều bình an.\n";
-    } else {
-        s += "Đêm qua, ";
-        s += list
+pub fn time_extended(secs: u64, remaining: u64) -> String {
+    format!(
+        "Lượt này được thêm {} phút {} giây. {}",
+        secs / 60,
+        secs % 60,
+        timeout(remaining)
+    )
+}
+
+pub fn phase_deadline(remaining: u64, deadline: i64) -> String {
+    format!(
+        "Lượt này kết thúc lúc {} (UTC). {}",
+        chrono::DateTime::from_timestamp(deadline, 0)
+            .unwrap_or_default()
+            .format("%H:%M %d/%m/%Y"),
+        timeout(remaining)
+    )
+}
+
+pub fn phase_reminder(remaining: u64, pending: &[i64]) -> String {
+    let mut s = format!("Sắp hết lượt! {}", timeout(remaining));
+    if !pending.is_empty() {
+        s += format!(
+            " Vẫn đang chờ {}.",
+            pending
+                .iter()
+                .map(|id| format!("<@{}>", id))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+        .as_str();
+    }
+    s
+}
+
+pub fn reveal_identities(pseudonyms: &HashMap<i64, Pseudonym>) -> String {
+    let mut list = pseudonyms.iter().collect::<Vec<(&i64, &Pseudonym)>>();
+    list.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+
+    let mut s = String::from("Danh tính thật của những người chơi:");
+    for (uid, p) in list {
+        s += format!("\n{} {} là <@{}>", p.avatar, p.name, uid).as_str();
+    }
+    s
+}
+
+pub fn bot_join(bot_id: i64, num_players: usize) -> String {
+    format!(
+        "Đã thêm người chơi máy <@{}> vào phòng, hiện có {} người chơi.",
+        bot_id, num_players
+    )
+}
+
+pub fn role_config_missing(num: usize) -> String {
+    format!(
+        "Không có cấu hình vai trò cho {} người chơi, hãy dùng bộ vai tùy chỉnh.",
+        num
+    )
+}
+
+pub fn role_config_broken(num: usize) -> String {
+    format!("Cấu hình vai trò cho {} người chơi bị lỗi.", num)
+}
+
+pub fn not_narrator() -> String {
+    "Chỉ người dẫn truyện mới dùng được lệnh này.".to_string()
+}
+
+pub fn new_narrator(narrator: Option<i64>) -> String {
+    match narrator {
+        Some(narrator) => format!("<@{}> sẽ là người dẫn truyện.", narrator),
+        None => "Trò chơi sẽ không có người dẫn truyện.".to_string(),
+    }
+}
+
+pub fn narrator_phase(prefix: &str) -> String {
+    format!(
+        "Hãy dùng `{}advance` để chuyển lượt, `{}override <kill | spare> <người chơi>` để quyết định thay kết quả.",
+        prefix, prefix
+    )
+}
+
+pub fn night_intent(user_id: i64, action: &str, targets: &[i64]) -> String {
+    format!(
+        "<@{}> dùng `{}` lên {}.",
+        user_id,
+        action,
+        targets
             .iter()
             .map(|id| format!("<@{}>", id))
             .collect::<Vec<String>>()
             .join(", ")
-            .as_str();
-        s += " đã bị mất tích một cách bí ẩn.\n";
-    }
+    )
+}
+
+pub fn override_set(target: i64, kill: bool) -> String {
+    format!(
+        "Cuối lượt này <@{}> sẽ {}.",
+        target,
+        if kill { "chết" } else { "được tha" }
+    )
+}
 
-    s += "===========================================================================";
+pub fn set_setting(key: &str, value: &str) -> String {
+    format!("Đã đặt `{}` thành `{}`.", key, value)
+}
 
-    s
+pub fn invalid_setting_key(key: &str, keys: &[&str]) -> String {
+    format!(
+        "Không có cài đặt `{}`, chọn một trong: {}.",
+        key,
+        keys.join(", ")
+    )
+}
+
+pub fn invalid_player_limits(min: usize, max: usize) -> String {
+    format!(
+        "Số người chơi tối thiểu ({}) không được lớn hơn tối đa ({}).",
+        min, max
+    )
+}
+
+pub fn out_of_range<T: std::fmt::Display>(min: T, max: T) -> String {
+    format!("Giá trị phải từ {} đến {}.", min, max)
+}
+
+pub fn invalid_value(value: &str) -> String {
+    format!("Giá trị `{}` không hợp lệ.", value)
 }
 
 pub fn must_alive() -> String {
@@ -336,21 +913,6 @@ pub fn reborned(user_id: i64) -> String {
     )
 }
 
-pub fn couple_died(died: i64, follow: i64, is_day: bool) -> String {
-    match is_day {
-        true => format!(
-            "Do <@{}> đã chết nên <@{}> cũng đã treo cổ tự vẫn đi theo tình yêu của đời mình.
-===========================================================================",
-            died, follow
-        ),
-        false => format!(
-            "<@{}> đã dừng cuộc chơi và bước trên con đường tìm kiếm <@{}>
-===========================================================================",
-            follow, died
-        ),
-    }
-}
-
 pub fn end_game(winner: &str) -> String {
     format!("Trò chơi kết thúc với chiến thắng thuộc về phe {}.", winner)
 }
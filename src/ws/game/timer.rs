@@ -0,0 +1,138 @@
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+// deadline of the current phase, frozen while the game is paused
+#[derive(Clone, Debug)]
+pub struct PhaseTimer {
+    deadline: Instant,
+    total: Duration,
+    paused: Option<Duration>,
+}
+
+impl PhaseTimer {
+    pub fn new(secs: u64) -> Self {
+        Self {
+            deadline: Instant::now() + Duration::from_secs(secs),
+            total: Duration::from_secs(secs),
+            paused: None,
+        }
+    }
+
+    // picks a phase back up from a unix deadline stored in the database
+    pub fn until(deadline: i64, total: u64) -> Self {
+        let secs = (deadline - Utc::now().timestamp()).max(0) as u64;
+        Self {
+            deadline: Instant::now() + Duration::from_secs(secs),
+            total: Duration::from_secs(total.max(secs)),
+            paused: None,
+        }
+    }
+
+    pub fn paused(remaining: u64, total: u64) -> Self {
+        let remaining = Duration::from_secs(remaining);
+        Self {
+            deadline: Instant::now() + remaining,
+            total: Duration::from_secs(total),
+            paused: Some(remaining),
+        }
+    }
+
+    pub fn deadline_unix(&self) -> i64 {
+        Utc::now().timestamp() + self.remaining_secs() as i64
+    }
+
+    pub fn remaining(&self) -> Duration {
+        match self.paused {
+            Some(remaining) => remaining,
+            None => self.deadline.saturating_duration_since(Instant::now()),
+        }
+    }
+
+    // whole seconds left, rounded up so the last second still shows as 1
+    pub fn remaining_secs(&self) -> u64 {
+        let remaining = self.remaining();
+        remaining.as_secs() + (remaining.subsec_nanos() > 0) as u64
+    }
+
+    pub fn total_secs(&self) -> u64 {
+        self.total.as_secs()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    pub fn pause(&mut self) -> bool {
+        if self.paused.is_some() {
+            return false;
+        }
+        self.paused = Some(self.remaining());
+        true
+    }
+
+    pub fn resume(&mut self) -> bool {
+        match self.paused.take() {
+            Some(remaining) => {
+                self.deadline = Instant::now() + remaining;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn extend(&mut self, secs: u64) {
+        let secs = Duration::from_secs(secs);
+        self.total += secs;
+        match self.paused.as_mut() {
+            Some(remaining) => *remaining += secs,
+            None => self.deadline += secs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_freezes_remaining() {
+        let mut timer = PhaseTimer::new(60);
+        assert!(timer.pause());
+        assert!(!timer.pause());
+        let remaining = timer.remaining();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(timer.remaining(), remaining);
+        assert!(timer.resume());
+        assert!(!timer.resume());
+        assert!(timer.remaining_secs() <= 60);
+    }
+
+    #[test]
+    fn extend_adds_to_total_and_remaining() {
+        let mut timer = PhaseTimer::new(30);
+        timer.extend(15);
+        assert_eq!(timer.total_secs(), 45);
+        assert_eq!(timer.remaining_secs(), 45);
+
+        timer.pause();
+        timer.extend(15);
+        assert_eq!(timer.total_secs(), 60);
+        assert_eq!(timer.remaining_secs(), 60);
+    }
+
+    #[test]
+    fn remaining_secs_rounds_up() {
+        let timer = PhaseTimer::new(10);
+        assert_eq!(timer.remaining_secs(), 10);
+        assert_eq!(PhaseTimer::new(0).remaining_secs(), 0);
+    }
+
+    #[test]
+    fn until_counts_from_the_deadline() {
+        let timer = PhaseTimer::until(Utc::now().timestamp() + 100, 50);
+        assert!((99..=100).contains(&timer.remaining_secs()));
+        assert_eq!(timer.total_secs(), 100);
+        assert_eq!(PhaseTimer::until(0, 50).remaining_secs(), 0);
+    }
+}
@@ -1,6 +1,10 @@
-use actix::{Context, Handler, Message};
+use actix::{Addr, Context, Handler, Message};
 
-use crate::ws::game::{cmds as game_cmds, text_templates as ttp};
+use crate::ws::game::{
+    cmds as game_cmds,
+    pseudonym::{mask_cmd, mask_mentions},
+    text_templates as ttp,
+};
 
 use super::{cmd_parser::Cmd, game::Game, services, ChatServer};
 
@@ -36,6 +40,9 @@ pub fn cmd_handler(
             let reply_to = reply_to
                 .map(|id| id.parse::<i64>().map_err(|err| err.to_string()))
                 .transpose()?;
+            // mentions of pseudonyms are stored with the real ids
+            let masks = srv.get_masks(channel_id, true);
+            let message = mask_mentions(&message, &masks, user_id);
             let chat = services::send_msg(
                 srv,
                 user_id,
@@ -58,6 +65,7 @@ pub fn cmd_handler(
 
             srv.broadcast(&bc, ws_id);
             srv.send_to(&rs, ws_id);
+            srv.mark_active(user_id, channel_id);
 
             if message.starts_with(srv.app_state.bot_prefix.as_str()) {
                 game_commands(
@@ -78,10 +86,12 @@ pub fn cmd_handler(
                 offset.unwrap_or(0) as i64,
                 limit.unwrap_or(50) as i64,
             )?;
-            let rs = Cmd::GetMsgRes {
+            let masks = srv.get_masks(channel_id.parse().unwrap_or(-1), false);
+            let mut rs = Cmd::GetMsgRes {
                 channel_id,
                 messages,
             };
+            mask_cmd(&mut rs, &masks, user_id);
 
             srv.send_to(&rs, ws_id);
         }
@@ -106,6 +116,9 @@ pub fn cmd_handler(
 
             srv.send_to(&Cmd::GetUsersRes(users), ws_id);
         }
+        Cmd::ListGames => {
+            srv.send_to(&Cmd::ListGamesRes(srv.list_games()), ws_id);
+        }
         Cmd::GetPers { channel_id } => {
             let channel_id = channel_id
                 .map(|id| id.parse::<i64>().map_err(|err| err.to_string()))
@@ -135,31 +148,153 @@ fn game_commands(
         .collect::<Vec<&str>>();
 
     match cmds[0] {
+        "create" => {
+            must_in_channel(1, channel_id)?;
+            if cmds.len() != 2 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "create <name | private>",
+                ));
+            }
+
+            if srv.get_user_game(user_id).is_some() {
+                return Err(ttp::in_other_game());
+            }
+
+            if cmds[1] == "private" {
+                let code = srv.gen_join_code();
+                let game = srv.new_game(ctx, code, true);
+                game.do_send(game_cmds::Join { user_id, msg_id });
+                srv.bot_send(
+                    channel_id,
+                    ttp::private_room_created(),
+                    Some(msg_id),
+                );
+                return Ok(());
+            }
+
+            if srv.find_game(cmds[1]).is_some() {
+                return Err(ttp::room_existed(cmds[1]));
+            }
+
+            let game = srv.new_game(ctx, cmds[1].to_string(), false);
+            game.do_send(game_cmds::Join { user_id, msg_id });
+        }
         "join" => {
             must_in_channel(1, channel_id)?;
-            let user_game = srv.get_user_game(user_id);
-            let cur_game = srv.current_game.as_ref();
+            if cmds.len() > 2 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "join <name | code>",
+                ));
+            }
 
-            if let (Some(game), Some(cur)) = (user_game, cur_game) {
-                if game != cur {
+            let game = match cmds.get(1) {
+                Some(name) => {
+                    srv.find_game(name).ok_or(ttp::room_not_found(name))?
+                }
+                None => srv
+                    .find_waiting_game()
+                    .ok_or(ttp::no_room(&srv.app_state.bot_prefix))?,
+            };
+
+            if let Some(user_game) = srv.get_user_game(user_id) {
+                if user_game != game {
                     return Err(ttp::in_other_game());
                 }
             }
+            if srv.find_narrated_game(user_id).is_some() {
+                return Err(ttp::is_narrator());
+            }
 
-            if let Some(game) = user_game {
-                return Ok(game.do_send(game_cmds::Join { user_id, msg_id }));
+            game.do_send(game_cmds::Join { user_id, msg_id });
+        }
+        "spectate" => {
+            must_in_channel(1, channel_id)?;
+            if cmds.len() != 2 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "spectate <name>",
+                ));
             }
 
-            match cur_game {
-                Some(game) => {
-                    game.do_send(game_cmds::Join { user_id, msg_id });
-                }
-                None => {
-                    let game = srv.new_game(ctx);
-                    srv.current_game = Some(game.clone());
-                    game.do_send(game_cmds::Join { user_id, msg_id });
-                }
+            if srv.get_user_game(user_id).is_some() {
+                return Err(ttp::in_other_game());
+            }
+            if srv.find_spectating_game(user_id).is_some() {
+                return Err(ttp::aready_spectating());
             }
+            if srv.find_narrated_game(user_id).is_some() {
+                return Err(ttp::is_narrator());
+            }
+
+            srv.find_game(cmds[1])
+                .ok_or(ttp::room_not_found(cmds[1]))?
+                .do_send(game_cmds::Spectate { user_id, msg_id });
+        }
+        "unspectate" => {
+            srv.find_spectating_game(user_id)
+                .ok_or(ttp::not_spectating())?
+                .do_send(game_cmds::Unspectate { user_id, msg_id });
+        }
+        "sub" => {
+            must_in_channel(1, channel_id)?;
+            if cmds.len() != 2 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "sub <player>",
+                ));
+            }
+
+            let target =
+                get_target(cmds[1])?.map_err(|_| ttp::must_mention())?;
+
+            if srv.get_user_game(user_id).is_some() {
+                return Err(ttp::in_other_game());
+            }
+            if srv.find_narrated_game(user_id).is_some()
+                || srv.find_spectating_game(user_id).is_some()
+            {
+                return Err(ttp::sub_not_allowed());
+            }
+
+            srv.find_player_game(target)
+                .ok_or(ttp::player_not_in_game(target))?
+                .do_send(game_cmds::Sub {
+                    user_id,
+                    msg_id,
+                    target,
+                });
+        }
+        "approve" => {
+            if cmds.len() != 2 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "approve <player>",
+                ));
+            }
+
+            let target = get_target(cmds[1])?;
+
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Approve {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    target,
+                },
+            )?;
+        }
+        "rooms" => {
+            srv.bot_send(
+                channel_id,
+                ttp::room_list(&srv.list_games()),
+                Some(msg_id),
+            );
         }
         "leave" => {
             must_in_channel(1, channel_id)?;
@@ -181,6 +316,116 @@ fn game_commands(
                     user_id,
                     msg_id,
                     channel_id,
+                    force: false,
+                },
+            )?;
+        }
+        "forcestart" => {
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Start {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    force: true,
+                },
+            )?;
+        }
+        "invite" => {
+            if cmds.len() != 2 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "invite <player>",
+                ));
+            }
+
+            let target = get_target(cmds[1])?;
+
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Invite {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    target,
+                },
+            )?;
+        }
+        "cancel" => {
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::CancelStart {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                },
+            )?;
+        }
+        "addbot" => {
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::AddBot {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                },
+            )?;
+        }
+        "kick" => {
+            if cmds.len() != 2 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "kick <player>",
+                ));
+            }
+
+            let target = get_target(cmds[1])?;
+
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Kick {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    target,
+                },
+            )?;
+        }
+        "host" => {
+            if cmds.len() != 2 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "host <player>",
+                ));
+            }
+
+            let target = get_target(cmds[1])?;
+
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Host {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    target,
                 },
             )?;
         }
@@ -210,6 +455,212 @@ fn game_commands(
                 },
             )?;
         }
+        "pause" | "resume" => {
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Pause {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    resume: cmds[0] == "resume",
+                },
+            )?;
+        }
+        "extend" => {
+            let secs = cmds
+                .get(1)
+                .filter(|_| cmds.len() == 2)
+                .and_then(|arg| parse_duration(arg))
+                .filter(|secs| (1..=24 * 3600).contains(secs))
+                .ok_or(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "extend <time>[s|m|h]",
+                ))?;
+
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Extend {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    secs,
+                },
+            )?;
+        }
+        "narrator" => {
+            if cmds.len() > 2 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "narrator [player]",
+                ));
+            }
+
+            let target = cmds.get(1).map(|arg| get_target(arg)).transpose()?;
+
+            // one narrator per user, and never while busy in another room
+            if let Some(Ok(target)) = target {
+                let game = srv.get_user_game(user_id);
+                let elsewhere = |other: Option<&Addr<Game>>| {
+                    other.is_some_and(|other| Some(other) != game)
+                };
+                if elsewhere(srv.get_user_game(target))
+                    || elsewhere(srv.find_narrated_game(target))
+                    || srv.find_spectating_game(target).is_some()
+                {
+                    return Err(ttp::user_busy(target));
+                }
+            }
+
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Narrator {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    target,
+                },
+            )?;
+        }
+        "advance" => {
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Advance {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                },
+            )?;
+        }
+        "override" => {
+            let kill = match cmds.get(1) {
+                Some(&"kill") if cmds.len() == 3 => true,
+                Some(&"spare") if cmds.len() == 3 => false,
+                _ => {
+                    return Err(ttp::wrong_cmd_format(
+                        &srv.app_state.bot_prefix,
+                        "override <kill | spare> <player>",
+                    ))
+                }
+            };
+
+            let target = get_target(cmds[2])?;
+
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Override {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    target,
+                    kill,
+                },
+            )?;
+        }
+        "set" => {
+            if cmds.len() != 3 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "set <key> <value>",
+                ));
+            }
+
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Set {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    key: cmds[1].to_string(),
+                    value: cmds[2].to_string(),
+                },
+            )?;
+        }
+        "mode" => {
+            if cmds.len() > 2 {
+                return Err(ttp::wrong_cmd_format(
+                    &srv.app_state.bot_prefix,
+                    "mode <name>",
+                ));
+            }
+
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Mode {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    name: cmds.get(1).map(|name| name.to_string()),
+                },
+            )?;
+        }
+        "roles" => {
+            let cmd = match (cmds.get(1), cmds.get(2), cmds.len()) {
+                (None, _, _) => game_cmds::DeckCmd::Show,
+                (Some(&"clear"), None, 2) => game_cmds::DeckCmd::Clear,
+                (Some(&"add"), Some(role), 3) => {
+                    game_cmds::DeckCmd::Add(role.to_string())
+                }
+                (Some(&"remove"), Some(role), 3) => {
+                    game_cmds::DeckCmd::Remove(role.to_string())
+                }
+                (Some(&"preset"), Some(name), 3) => {
+                    game_cmds::DeckCmd::Preset(name.to_string())
+                }
+                _ => {
+                    return Err(ttp::wrong_cmd_format(
+                        &srv.app_state.bot_prefix,
+                        "roles [add <role> | remove <role> | preset <name> | clear]",
+                    ))
+                }
+            };
+
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Roles {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                    cmd,
+                },
+            )?;
+        }
+        "settings" => {
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Settings {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                },
+            )?;
+        }
         "vote" => {
             if cmds.len() != 2 {
                 return Err(ttp::wrong_cmd_format(
@@ -233,6 +684,32 @@ fn game_commands(
                 },
             )?;
         }
+        "unvote" => {
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Unvote {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                },
+            )?;
+        }
+        "votes" => {
+            send_cmd(
+                srv,
+                user_id,
+                channel_id,
+                msg_id,
+                game_cmds::Votes {
+                    user_id,
+                    msg_id,
+                    channel_id,
+                },
+            )?;
+        }
         "kill" => {
             if cmds.len() != 2 {
                 return Err(ttp::wrong_cmd_format(
@@ -402,12 +879,11 @@ where
     M::Result: Send,
     Game: Handler<M>,
 {
-    if let Some(game) = srv.get_user_game(user_id) {
-        game.do_send(cmd);
-        return Ok(());
-    }
-
-    match srv.current_game.as_ref() {
+    // the narrator isn't a player, so look for the game they narrate too
+    let game = srv
+        .get_user_game(user_id)
+        .or_else(|| srv.find_narrated_game(user_id));
+    match game {
         Some(game) => {
             game.do_send(cmd);
         }
@@ -419,6 +895,17 @@ where
     Ok(())
 }
 
+// seconds unless the number ends with m or h, e.g. 90, 30m, 2h
+fn parse_duration(arg: &str) -> Option<u64> {
+    let (num, unit) = match arg.char_indices().last()? {
+        (i, 's') => (&arg[..i], 1),
+        (i, 'm') => (&arg[..i], 60),
+        (i, 'h') => (&arg[..i], 3600),
+        _ => (arg, 1),
+    };
+    num.parse::<u64>().ok()?.checked_mul(unit)
+}
+
 fn get_target(arg: &str) -> Result<Result<i64, u16>, String> {
     if let Ok(id) = arg.parse() {
         Ok(Err(id))
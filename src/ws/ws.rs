@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use actix::{
@@ -10,12 +11,21 @@ use actix_web_actors::ws::{
     Message as WsMessage, ProtocolError, WebsocketContext,
 };
 
+use rand::Rng;
+
 use crate::config::{AppState, DbPool};
+use crate::models::game::GameDisplay;
 
 use super::{
     cmd_parser::{Cmd, GameEvent},
-    game::{cmds, Game},
-    message_handler::msg_handler,
+    game::{
+        cmds,
+        game::{GameChannel, GameInfo, GameOptions},
+        game_loop::GameLoop,
+        pseudonym::{mask_cmd, mask_event, Masks},
+        Game,
+    },
+    message_handler::{cmd_handler, msg_handler},
     services,
 };
 
@@ -45,11 +55,18 @@ pub struct ClientMsg {
     msg: String,
 }
 
+pub struct GameRoom {
+    pub name: String,
+    pub addr: Addr<Game>,
+    pub info: Arc<Mutex<GameInfo>>,
+}
+
 pub struct ChatServer {
     pub clients: HashMap<i64, Recipient<Msg>>,
     pub users: HashMap<i64, Vec<i64>>,
-    pub games: HashMap<i64, Addr<Game>>,
-    pub current_game: Option<Addr<Game>>,
+    pub games: HashMap<i64, GameRoom>,
+    // channel id to the id of the game owning it
+    pub channel_games: HashMap<i64, i64>,
     pub app_state: AppState,
     pub db_pool: DbPool,
 }
@@ -60,48 +77,78 @@ impl ChatServer {
             clients: HashMap::new(),
             users: HashMap::new(),
             games: HashMap::new(),
-            current_game: None,
+            channel_games: HashMap::new(),
             app_state,
             db_pool,
         }
     }
 
     pub fn broadcast(&self, cmd: &Cmd, except: i64) {
-        let uids = match &cmd {
+        let (uids, masks) = match &cmd {
             Cmd::BroadCastMsg { channel_id, .. } => {
-                services::get_channel_users(
-                    self,
-                    channel_id.parse().unwrap_or(-1),
-                )
-                .iter()
-                .map(|u| u.id)
-                .collect()
+                let channel_id = channel_id.parse().unwrap_or(-1);
+                let uids = services::get_channel_users(self, channel_id)
+                    .iter()
+                    .map(|u| u.id)
+                    .collect();
+                (uids, self.get_masks(channel_id, false))
             }
-            _ => self.users.keys().cloned().collect::<Vec<i64>>(),
+            _ => (
+                self.users.keys().cloned().collect::<Vec<i64>>(),
+                Masks::new(),
+            ),
         };
-        let allow_wsi = uids
+        let msg = cmd.to_string();
+
+        for uid in uids {
+            let ws = match self.users.get(&uid) {
+                Some(ws) => ws,
+                None => continue,
+            };
+            let msg = if masks.is_empty() {
+                msg.clone()
+            } else {
+                let mut cmd = cmd.clone();
+                mask_cmd(&mut cmd, &masks, uid);
+                cmd.to_string()
+            };
+            for ws_id in ws.iter().filter(|&&ws_id| ws_id != except) {
+                if let Some(client) = self.clients.get(ws_id) {
+                    client.do_send(Msg(msg.clone())).ok();
+                }
+            }
+        }
+    }
+
+    // sends news about a user to everyone but the user's own sockets and
+    // the people who only know the user by a pseudonym
+    pub fn broadcast_user(&self, cmd: &Cmd, user_id: i64) {
+        let mut excepts = self.anonymous_peers(user_id);
+        excepts.insert(user_id);
+        let excepts = excepts
             .iter()
-            .map(|id| self.users.get(&id).unwrap_or(&vec![]).clone())
+            .filter_map(|uid| self.users.get(uid))
             .flatten()
-            .collect::<Vec<i64>>();
-
+            .collect::<HashSet<&i64>>();
+        let msg = cmd.to_string();
         for (ws_id, client) in self.clients.iter() {
-            if *ws_id != except && allow_wsi.contains(ws_id) {
-                client.do_send(Msg(cmd.to_string())).ok();
+            if !excepts.contains(ws_id) {
+                client.do_send(Msg(msg.clone())).ok();
             }
         }
     }
 
-    pub fn broadcast_user(&self, cmd: &Cmd, except: i64) {
-        if let Some(excepts) = self.users.get(&except) {
-            for (ws_id, client) in self.clients.iter() {
-                if !excepts.contains(ws_id) {
-                    client.do_send(Msg(cmd.to_string())).ok();
-                }
+    // players and spectators of the anonymous games the user plays in
+    fn anonymous_peers(&self, user_id: i64) -> HashSet<i64> {
+        let mut peers = HashSet::new();
+        for room in self.games.values() {
+            let info = room.info.lock().unwrap();
+            if info.get_masks(false).contains_key(&user_id) {
+                peers.extend(info.users.iter().chain(info.spectators.iter()));
             }
-        } else {
-            self.broadcast(cmd, -1);
         }
+        peers.remove(&user_id);
+        peers
     }
 
     pub fn send_to(&self, cmd: &Cmd, ws_id: i64) {
@@ -120,6 +167,25 @@ impl ChatServer {
         }
     }
 
+    // ids of the anonymous game owning the channel, see GameInfo
+    pub fn get_masks(&self, channel_id: i64, reverse: bool) -> Masks {
+        self.channel_games
+            .get(&channel_id)
+            .and_then(|game_id| self.games.get(game_id))
+            .map(|room| room.info.lock().unwrap().get_masks(reverse))
+            .unwrap_or_default()
+    }
+
+    // game channels are made with the lobby and when the game starts
+    fn index_channels(&mut self, game_id: i64) {
+        if let Some(room) = self.games.get(&game_id) {
+            let info = room.info.lock().unwrap();
+            for &channel_id in info.channels.values() {
+                self.channel_games.insert(channel_id, game_id);
+            }
+        }
+    }
+
     pub fn user_online(&self, user_id: i64) {
         if let Ok(u) = services::get_info(self, user_id) {
             self.broadcast_user(&Cmd::UserOnline(u), user_id);
@@ -161,7 +227,12 @@ impl ChatServer {
         self.send_to_user(&Cmd::GetPersRes(pers), user_id);
     }
 
-    pub fn new_game(&mut self, ctx: &mut Context<Self>) -> Addr<Game> {
+    pub fn new_game(
+        &mut self,
+        ctx: &mut Context<Self>,
+        name: String,
+        is_private: bool,
+    ) -> Addr<Game> {
         let game_id = self
             .app_state
             .id_generatator
@@ -169,23 +240,147 @@ impl ChatServer {
             .unwrap()
             .real_time_generate();
 
+        let options = GameOptions {
+            id: game_id,
+            name,
+            is_private,
+        };
         let game = Game::new(
-            game_id,
+            options,
             ctx.address(),
             self.db_pool.clone(),
             self.app_state.id_generatator.clone(),
             self.app_state.bot_id,
             self.app_state.bot_prefix.clone(),
-        )
-        .start();
-        self.games.insert(game_id, game.clone());
+        );
+        self.add_game(game)
+    }
 
-        game
+    pub fn add_game(&mut self, game: Game) -> Addr<Game> {
+        let id = game.id;
+        let name = game.name.clone();
+        let info = game.info.clone();
+        let addr = game.start();
+        self.games.insert(
+            id,
+            GameRoom {
+                name,
+                addr: addr.clone(),
+                info,
+            },
+        );
+        self.index_channels(id);
+
+        addr
     }
 
     pub fn get_user_game(&self, user_id: i64) -> Option<&Addr<Game>> {
         let game_id = services::get_game_from_user(self, user_id)?;
-        self.games.get(&game_id)
+        self.games.get(&game_id).map(|room| &room.addr)
+    }
+
+    pub fn find_game(&self, name: &str) -> Option<&Addr<Game>> {
+        self.games
+            .values()
+            .find(|room| room.name == name)
+            .map(|room| &room.addr)
+    }
+
+    // a message in the game's channels counts as activity for the idle
+    // check, the personal one included since night actions go there
+    pub fn mark_active(&self, user_id: i64, channel_id: i64) {
+        let room = match self
+            .channel_games
+            .get(&channel_id)
+            .and_then(|game_id| self.games.get(game_id))
+        {
+            Some(room) => room,
+            None => return,
+        };
+        let mut info = room.info.lock().unwrap();
+        let counts = info.channels.iter().any(|(kind, &id)| {
+            id == channel_id
+                && match kind {
+                    GameChannel::GamePlay
+                    | GameChannel::WereWolf
+                    | GameChannel::Narrator => true,
+                    GameChannel::Personal(uid) => *uid == user_id,
+                    GameChannel::Cemetery => false,
+                }
+        });
+        if counts && info.is_started && info.users.contains(&user_id) {
+            info.active.insert(user_id);
+        }
+    }
+
+    pub fn find_player_game(&self, user_id: i64) -> Option<&Addr<Game>> {
+        self.games
+            .values()
+            .find(|room| room.info.lock().unwrap().users.contains(&user_id))
+            .map(|room| &room.addr)
+    }
+
+    pub fn find_narrated_game(&self, user_id: i64) -> Option<&Addr<Game>> {
+        self.games
+            .values()
+            .find(|room| room.info.lock().unwrap().narrator == Some(user_id))
+            .map(|room| &room.addr)
+    }
+
+    pub fn find_spectating_game(&self, user_id: i64) -> Option<&Addr<Game>> {
+        self.games
+            .values()
+            .find(|room| {
+                room.info.lock().unwrap().spectators.contains(&user_id)
+            })
+            .map(|room| &room.addr)
+    }
+
+    pub fn find_waiting_game(&self) -> Option<&Addr<Game>> {
+        let mut ids = self.games.keys().collect::<Vec<&i64>>();
+        ids.sort();
+        ids.into_iter()
+            .map(|id| self.games.get(id).unwrap())
+            .find(|room| {
+                let info = room.info.lock().unwrap();
+                !info.is_private && info.get_state() == "waiting"
+            })
+            .map(|room| &room.addr)
+    }
+
+    // private rooms are named by their join code
+    pub fn gen_join_code(&self) -> String {
+        const CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+        loop {
+            let code = (0..6)
+                .map(|_| {
+                    let i = rand::thread_rng().gen_range(0..CHARSET.len());
+                    CHARSET[i] as char
+                })
+                .collect::<String>();
+            if self.find_game(&code).is_none() {
+                return code;
+            }
+        }
+    }
+
+    pub fn list_games(&self) -> Vec<GameDisplay> {
+        let mut games = self
+            .games
+            .iter()
+            .filter(|(_, room)| !room.info.lock().unwrap().is_private)
+            .map(|(id, room)| {
+                let info = room.info.lock().unwrap();
+                GameDisplay {
+                    id: id.to_string(),
+                    name: room.name.clone(),
+                    num_players: info.users.len(),
+                    state: info.get_state().to_string(),
+                }
+            })
+            .collect::<Vec<GameDisplay>>();
+        games.sort_by(|a, b| a.name.cmp(&b.name));
+        games
     }
 }
 
@@ -193,17 +388,18 @@ impl Actor for ChatServer {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
-        if let Some(game) = Game::load_from_db(
+        for game in Game::load_all_from_db(
             ctx.address(),
             self.db_pool.clone(),
             self.app_state.id_generatator.clone(),
             self.app_state.bot_id,
             self.app_state.bot_prefix.clone(),
         ) {
-            let id = game.id;
-            let addr = game.start();
-            self.games.insert(id, addr.clone());
-            self.current_game = Some(addr);
+            // games restored from a snapshot pick up their running phase
+            if game.info.lock().unwrap().is_started {
+                actix::Arbiter::spawn(GameLoop::resume(game.clone()));
+            }
+            self.add_game(game);
         }
     }
 }
@@ -225,6 +421,12 @@ impl Handler<Connect> for ChatServer {
         if let Some(ws) = self.users.get(&msg.user_id) {
             if ws.len() == 1 {
                 self.user_online(msg.user_id);
+                if let Some(game) = self.get_user_game(msg.user_id) {
+                    game.do_send(cmds::Away {
+                        user_id: msg.user_id,
+                        is_away: false,
+                    });
+                }
             }
         }
 
@@ -246,6 +448,12 @@ impl Handler<Disconnect> for ChatServer {
             if ws.is_empty() {
                 self.users.remove(&msg.user_id);
                 self.user_offline(msg.user_id);
+                if let Some(game) = self.get_user_game(msg.user_id) {
+                    game.do_send(cmds::Away {
+                        user_id: msg.user_id,
+                        is_away: true,
+                    });
+                }
             }
         }
     }
@@ -292,23 +500,67 @@ impl Handler<cmds::GameMsg> for ChatServer {
             uids = uids_v;
             msg.event = GameEvent::StopGame;
         }
+        let mut masks = Masks::new();
+        if let Some(room) = self.games.get(&msg.game_id) {
+            // spectators never get what is meant for a single player
+            let info = room.info.lock().unwrap();
+            if !matches!(msg.event, GameEvent::NightResult(..)) {
+                uids.extend(info.spectators.iter());
+            }
+            uids.extend(info.narrator.iter());
+            masks = info.get_masks(false);
+        }
 
         let event = msg.event.clone();
-        let cmd = &Cmd::GameEvent(msg.event);
+        let game_id = msg.game_id;
+        let cmd = Cmd::GameEvent(msg.event).to_string();
 
         match event.clone() {
             GameEvent::UserJoin(uid_s) => self.send_to_user(
                 &Cmd::GameEvent(GameEvent::JoinGame(msg.game_id.to_string())),
                 uid_s.parse().unwrap(),
             ),
-            GameEvent::UserLeave(uid_s) => self.send_to_user(
-                &Cmd::GameEvent(GameEvent::LeaveGame(msg.game_id.to_string())),
+            GameEvent::UserLeave(uid_s) | GameEvent::UserKicked(uid_s) => self
+                .send_to_user(
+                    &Cmd::GameEvent(GameEvent::LeaveGame(
+                        msg.game_id.to_string(),
+                    )),
+                    uid_s.parse().unwrap(),
+                ),
+            GameEvent::PlayerSubstituted(old, new) => {
+                self.send_to_user(
+                    &Cmd::GameEvent(GameEvent::LeaveGame(
+                        msg.game_id.to_string(),
+                    )),
+                    old.parse().unwrap(),
+                );
+                self.send_to_user(
+                    &Cmd::GameEvent(GameEvent::JoinGame(
+                        msg.game_id.to_string(),
+                    )),
+                    new.parse().unwrap(),
+                );
+            }
+            GameEvent::NewNarrator(Some(uid_s)) => self.send_to_user(
+                &Cmd::GameEvent(GameEvent::JoinGame(msg.game_id.to_string())),
+                uid_s.parse().unwrap(),
+            ),
+            GameEvent::SpectatorJoin(uid_s) => self.send_to_user(
+                &Cmd::GameEvent(GameEvent::SpectateGame(
+                    msg.game_id.to_string(),
+                )),
                 uid_s.parse().unwrap(),
             ),
-            GameEvent::StartGame => self.current_game = None,
+            GameEvent::SpectatorLeave(uid_s) => self.send_to_user(
+                &Cmd::GameEvent(GameEvent::UnspectateGame(
+                    msg.game_id.to_string(),
+                )),
+                uid_s.parse().unwrap(),
+            ),
+            GameEvent::StartGame => self.index_channels(msg.game_id),
             GameEvent::StopGame => {
                 self.games.remove(&msg.game_id);
-                self.current_game = None;
+                self.channel_games.retain(|_, &mut id| id != game_id);
             }
             _ => {}
         }
@@ -317,11 +569,18 @@ impl Handler<cmds::GameMsg> for ChatServer {
             if !uids.contains(uid) {
                 continue;
             }
+            let cmd = if masks.is_empty() {
+                cmd.clone()
+            } else {
+                let mut event = event.clone();
+                mask_event(&mut event, &masks, *uid);
+                Cmd::GameEvent(event).to_string()
+            };
             for (wsi, client) in self.clients.iter() {
                 if !ws.contains(wsi) {
                     continue;
                 }
-                client.do_send(Msg(cmd.to_string())).ok();
+                client.do_send(Msg(cmd.clone())).ok();
                 if let GameEvent::EndGame { .. } = event {
                     if let Ok(user) = services::get_info(self, *uid) {
                         client
@@ -334,6 +593,43 @@ impl Handler<cmds::GameMsg> for ChatServer {
     }
 }
 
+impl Handler<cmds::BotPlay> for ChatServer {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: cmds::BotPlay,
+        ctx: &mut Self::Context,
+    ) -> Self::Result {
+        // bots have no socket, -1 sends the echo nowhere
+        let cmd = Cmd::SendReq {
+            channel_id: msg.channel_id.to_string(),
+            message: msg.message,
+            reply_to: None,
+        };
+        if let Err(err) = cmd_handler(self, ctx, -1, msg.user_id, cmd) {
+            self.bot_send(msg.channel_id, err, None);
+        }
+    }
+}
+
+impl Handler<cmds::InviteMsg> for ChatServer {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: cmds::InviteMsg,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let cmd = Cmd::GameInvite {
+            game_id: msg.game_id.to_string(),
+            code: msg.code,
+            from: msg.from.to_string(),
+        };
+        self.send_to_user(&cmd, msg.to);
+    }
+}
+
 impl Handler<cmds::UpdatePers> for ChatServer {
     type Result = ();
 